@@ -146,15 +146,110 @@ class RoutePanel extends HTMLElement {
         let totalDistanceSafe = viMain.calculateTotalDistance(safeCoordinates).toFixed(1);
         viMain.clearDistanceCache();
         let totalDistanceFast = fastCoordinates ? viMain.calculateTotalDistance(fastCoordinates).toFixed(1) : null;
-        let totalDurationSafe = totalDistanceSafe / 15.0
-        let totalDurationFast = totalDistanceFast ? totalDistanceFast / 15.0 : null;
+        // Durées estimées par le serveur (en secondes) selon la pente et le revêtement
+        let durations = null;
+        try {
+            durations = JSON.parse(this.getAttribute('durations') || 'null');
+        } catch (e) {
+            console.error('RoutePanel: invalid durations JSON:', e);
+        }
+        // Part du trajet en LTS 1 ou 2 (faible stress), pondérée par la distance des tronçons
+        let legs = null;
+        try {
+            legs = JSON.parse(this.getAttribute('legs') || 'null');
+        } catch (e) {
+            console.error('RoutePanel: invalid legs JSON:', e);
+        }
+        const lowStress = (routeLegs) => {
+            if (!routeLegs) {
+                return '';
+            }
+            let low = 0;
+            let total = 0;
+            for (const leg of routeLegs) {
+                if (!leg.lts) {
+                    continue;
+                }
+                low += leg.lts.distances[0] + leg.lts.distances[1];
+                total += leg.lts.distances.reduce((a, b) => a + b, 0);
+            }
+            return total > 0 ? `${Math.round(100 * low / total)} % LTS 1–2` : '';
+        };
+        // Barre empilée des kilomètres par type d'infrastructure
+        const infrastructureKinds = [
+            ['track', 'piste', '#1b7f3b'],
+            ['lane', 'bande', '#6cc24a'],
+            ['shared_lane', 'chaussée désignée', '#b5e08a'],
+            ['residential', 'rue locale', '#9db3c8'],
+            ['arterial', 'artère', '#e07b39'],
+            ['ferry', 'traversier', '#3b7bd4'],
+            ['other', 'autre', '#bbbbbb'],
+        ];
+        const infrastructureBar = (routeLegs) => {
+            if (!routeLegs) {
+                return '';
+            }
+            const totals = {};
+            for (const leg of routeLegs) {
+                for (const [kind] of infrastructureKinds.concat([['road_work'], ['reported']])) {
+                    totals[kind] = (totals[kind] || 0) + (leg.infrastructure ? leg.infrastructure[kind] : 0);
+                }
+            }
+            const total = infrastructureKinds.reduce((sum, [kind]) => sum + totals[kind], 0);
+            if (total <= 0) {
+                return '';
+            }
+            const segments = infrastructureKinds
+                .filter(([kind]) => totals[kind] > 0)
+                .map(([kind, label, color]) => html`<div title="${label}: ${(totals[kind] / 1000).toFixed(1)} km" style="width: ${100 * totals[kind] / total}%; background: ${color};"></div>`)
+                .join('');
+            const warnings = [
+                totals.road_work > 0 ? `travaux: ${(totals.road_work / 1000).toFixed(1)} km` : '',
+                totals.reported > 0 ? `signalé: ${(totals.reported / 1000).toFixed(1)} km` : '',
+            ].filter((warning) => warning).join(', ');
+            return html`
+                <div style="display: flex; height: 0.5em; width: 100%; border-radius: 0.25em; overflow: hidden; margin-top: 0.3em;">${segments}</div>
+                <div style="font-size: 0.8em;">${warnings}</div>
+            `;
+        };
+        // Signalements, entraves et neige qui restent sur chaque itinéraire
+        let hazards = null;
+        try {
+            hazards = JSON.parse(this.getAttribute('hazards') || 'null');
+        } catch (e) {
+            console.error('RoutePanel: invalid hazards JSON:', e);
+        }
+        const hazardLabels = { report: 'signalement', road_work: 'travaux', snow: 'neige' };
+        const hazardSummary = (routeHazards) => {
+            if (!routeHazards || routeHazards.length === 0) {
+                return '';
+            }
+            const items = routeHazards.map((hazard) => {
+                let text = `${hazardLabels[hazard.kind]} à ${(hazard.position / 1000).toFixed(1)} km`;
+                if (hazard.name) {
+                    text += ` (${hazard.name})`;
+                }
+                if (hazard.score !== null && hazard.score !== undefined) {
+                    text += `, cote ${hazard.score}`;
+                }
+                if (hazard.comment) {
+                    text += `: ${hazard.comment}`;
+                }
+                return html`<div>⚠ ${text.replace(/&/g, '&amp;').replace(/</g, '&lt;')}</div>`;
+            }).join('');
+            return html`<div style="font-size: 0.8em; text-align: left;">${items}</div>`;
+        };
+        const lowStressSafe = lowStress(legs && legs[0]);
+        const lowStressFast = lowStress(legs && legs[1]);
+        let totalDurationSafe = durations && durations[0] ? durations[0] / 3600.0 : totalDistanceSafe / 15.0
+        let totalDurationFast = totalDistanceFast ? (durations && durations[1] ? durations[1] / 3600.0 : totalDistanceFast / 15.0) : null;
         let durationStringSafe = "";
         let hours = Math.floor(totalDurationSafe);
         let minutes = Math.round((totalDurationSafe - hours) * 60.0)
         if (hours >= 1.0) {
             durationStringSafe = hours + " heures et "
         }
-        durationStringSafe += ` ${minutes} minutes à 15 km/h`
+        durationStringSafe += ` ${minutes} minutes`
         let durationStringFast = "";
         if (totalDurationFast) {
             hours = Math.floor(totalDurationFast);
@@ -162,7 +257,7 @@ class RoutePanel extends HTMLElement {
             if (hours >= 1.0) {
                 durationStringFast = hours + " heures et "
             }
-            durationStringFast += ` ${minutes} minutes à 15 km/h`
+            durationStringFast += ` ${minutes} minutes`
         }
 
         // Construire le bouton rapide seulement s'il existe une route rapide
@@ -178,6 +273,9 @@ class RoutePanel extends HTMLElement {
                 <div style="font-size: 0.9em;">
                     Longueur: <span style="font-weight: bold; font-size: 1.3em;">${totalDistanceFast}</span> kms
                 </div>
+                <div style="font-size: 0.8em;">${lowStressFast}</div>
+                ${infrastructureBar(legs && legs[1])}
+                ${hazardSummary(hazards && hazards[1])}
                 ${errorText}
             </md-filled-button>
         ` : '';
@@ -193,6 +291,9 @@ class RoutePanel extends HTMLElement {
                         <div style="font-size: 0.9em;">
                             Longueur: <span style="font-weight: bold; font-size: 1.3em;">${totalDistanceSafe}</span> kms
                         </div>
+                        <div style="font-size: 0.8em;">${lowStressSafe}</div>
+                        ${infrastructureBar(legs && legs[0])}
+                        ${hazardSummary(hazards && hazards[0])}
                         ${errorText}
                     </md-filled-button>
                     ${fastRouteButton}
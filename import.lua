@@ -438,6 +438,37 @@ local all_node = osm2pgsql.define_node_table('all_node', {
     }
 })
 
+-- Restrictions de virage (relations type=restriction) dont le via est un noeud,
+-- une ligne par paire de ways from/to
+local turn_restriction = osm2pgsql.define_relation_table('turn_restriction', {
+    {
+        column = 'restriction',
+        type = 'text',
+        not_null = true
+    }, {
+        column = 'from_way',
+        type = 'int8',
+        not_null = true
+    }, {
+        column = 'via_node',
+        type = 'int8',
+        not_null = true
+    }, {
+        column = 'to_way',
+        type = 'int8',
+        not_null = true
+    }
+})
+
+-- Barrières, traversées et feux, reportés sur les edges du graphe par import.sh
+local routing_node = osm2pgsql.define_node_table('routing_node', {
+    {
+        column = 'tags',
+        type = 'jsonb',
+        not_null = true
+    }
+})
+
 local address = osm2pgsql.define_table({
     name = 'address',
     ids = {
@@ -705,7 +736,60 @@ function osm2pgsql.process_way(way)
 
 end
 
+-- Restriction qui s'applique aux vélos: restriction:bicycle a priorité,
+-- et except=bicycle exempte de la restriction générale
+local function bicycle_restriction(tags)
+    if tags["restriction:bicycle"] then
+        return tags["restriction:bicycle"]
+    end
+    if tags.except then
+        for value in string.gmatch(tags.except, "[^;]+") do
+            if value:match("^%s*(.-)%s*$") == "bicycle" then
+                return nil
+            end
+        end
+    end
+    return tags.restriction
+end
+
+local function process_turn_restriction(relation)
+    local restriction = bicycle_restriction(relation.tags)
+    if not restriction or not (restriction:find("^no_") or restriction:find("^only_")) then
+        return
+    end
+    local from_ways = {}
+    local to_ways = {}
+    local vias = {}
+    for _, member in ipairs(relation.members) do
+        if member.role == "from" and member.type == "w" then
+            table.insert(from_ways, member.ref)
+        elseif member.role == "to" and member.type == "w" then
+            table.insert(to_ways, member.ref)
+        elseif member.role == "via" then
+            table.insert(vias, member)
+        end
+    end
+    -- Les restrictions dont le via est un way ne sont pas gérées
+    if #vias ~= 1 or vias[1].type ~= "n" then
+        return
+    end
+    for _, from_way in ipairs(from_ways) do
+        for _, to_way in ipairs(to_ways) do
+            turn_restriction:insert({
+                restriction = restriction,
+                from_way = from_way,
+                via_node = vias[1].ref,
+                to_way = to_way
+            })
+        end
+    end
+end
+
 function osm2pgsql.process_relation(relation)
+    if relation.tags.type == "restriction" then
+        process_turn_restriction(relation)
+        return
+    end
     if relation.tags.landuse == "forest" or relation.tags.landuse == "cemetery" or relation.tags.natural == "wood" or
         relation.tags.natural == "water" or relation.tags.natural == "bay" or relation.tags.leisure == "park" or relation.tags.landuse == "residential" or
         relation.tags.natural == "strait" or relation.tags.ocean == "yes" then
@@ -760,6 +844,11 @@ function osm2pgsql.process_relation(relation)
     end
 end
 
+local function is_routing_node(tags)
+    return tags.barrier or tags.kerb or tags.crossing or tags.highway == "traffic_signals" or
+        tags.highway == "crossing"
+end
+
 function osm2pgsql.process_node(node)
     if node.tags.place or node.tags.amenity or node.tags.shop == "bicycle" then
         all_node:insert({
@@ -775,6 +864,12 @@ function osm2pgsql.process_node(node)
         })
     end
 
+    if is_routing_node(node.tags) then
+        routing_node:insert({
+            tags = node.tags
+        })
+    end
+
     if node.tags.place == "ocean" or node.tags.place == "sea" then
         water_name:insert({
             name = node.tags.name,
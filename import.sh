@@ -44,6 +44,9 @@ download_if_missing "$MAINE_URL" "$MAINE_FILE"
 download_if_missing "$VERMONT_URL" "$VERMONT_FILE"
 download_if_missing "$NEWYORK_URL" "$NEWYORK_FILE"
 
+# Horodatage des données OSM (en-tête Geofabrik), conservé dans import_info pour l'instantané du graphe
+OSM_TIMESTAMP=$(osmium fileinfo -g header.option.osmosis_replication_timestamp "$QUEBEC_FILE" || true)
+
 echo "   -> Extractions géographiques ciblées..."
 osmium extract --bbox -79.0,43.0,-71.0,45.5 "$NEWYORK_FILE" -o "$NY_NORTH_FILE" --overwrite
 
@@ -117,6 +120,11 @@ WHERE EXISTS (SELECT 1 FROM public.srtm_elevation_polygons LIMIT 1);
 INSERT INTO import.srtm_boundary SELECT ST_GeomFromText('POLYGON EMPTY', 3857)
 WHERE NOT EXISTS (SELECT 1 FROM import.srtm_boundary);
 
+-- Horodatage des données OSM importées
+DROP TABLE IF EXISTS import.import_info;
+CREATE TABLE import.import_info AS
+SELECT NULLIF('$OSM_TIMESTAMP', '')::timestamptz as osm_timestamp, now() as imported_at;
+
 -- C. Structure EDGE (Tout en 3857)
 CREATE SEQUENCE IF NOT EXISTS edge_id;
 DROP TABLE IF EXISTS import.edge CASCADE;
@@ -126,15 +134,19 @@ CREATE TABLE import.edge (
     x1 double precision, y1 double precision,
     x2 double precision, y2 double precision,
     way_id bigint, tags jsonb, geom geometry(LineString, 3857),
-    city_name text, in_bicycle_route boolean,
-    elevation_start smallint, elevation_end smallint
+    city_name text, in_bicycle_route boolean, in_route_verte boolean,
+    elevation_start smallint, elevation_end smallint,
+    source_node_tags jsonb, target_node_tags jsonb
 );
 
+-- Noeuds qui portent une barrière, une traversée ou des feux (voir import.lua)
+CREATE INDEX ON import.routing_node (node_id);
+
 -- D. INSERTION MASSIVE (Performance Maximale)
 INSERT INTO import.edge
 WITH segments AS (
     SELECT 
-        aw.way_id, aw.tags, aw.in_bicycle_route,
+        aw.way_id, aw.tags, aw.in_bicycle_route, aw.in_route_verte,
         aw.nodes[(segment.path)[1]] as s_id,
         aw.nodes[(segment.path)[1] + 1] as t_id,
         ST_PointN(segment.geom, 1) as p1,
@@ -149,8 +161,9 @@ SELECT
     ST_X(s.p1), ST_Y(s.p1), 
     ST_X(s.p2), ST_Y(s.p2),
     s.way_id, s.tags, s.s_geom,
-    c.name, s.in_bicycle_route,
-    r1.elevation::smallint, r2.elevation::smallint
+    c.name, s.in_bicycle_route, s.in_route_verte,
+    r1.elevation::smallint, r2.elevation::smallint,
+    n1.tags, n2.tags
 FROM segments s
 -- Jointure ville (3857 vs 3857)
 LEFT JOIN import.city_subdivided c ON ST_Intersects(s.p1, c.geom)
@@ -166,7 +179,9 @@ LEFT JOIN LATERAL (
     WHERE ST_Intersects(s.p2, (SELECT geom FROM bounds))
     AND ST_Covers(geom, s.p2) 
     LIMIT 1
-) r2 ON true;
+) r2 ON true
+LEFT JOIN import.routing_node n1 ON n1.node_id = s.s_id
+LEFT JOIN import.routing_node n2 ON n2.node_id = s.t_id;
 
 -- E. Indexation et Statistiques
 CREATE INDEX ON import.edge USING GIST (geom);
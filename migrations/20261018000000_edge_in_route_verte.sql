@@ -0,0 +1,12 @@
+-- Ajoute in_route_verte aux edges pour que le routage puisse tenir compte de la Route Verte.
+-- L'import (import.sh) crée la colonne directement; ceci couvre une base déjà importée.
+DO $$
+BEGIN
+    IF to_regclass('public.edge') IS NOT NULL THEN
+        ALTER TABLE edge ADD COLUMN IF NOT EXISTS in_route_verte boolean NOT NULL DEFAULT false;
+        UPDATE edge e
+        SET in_route_verte = true
+        FROM all_way aw
+        WHERE aw.way_id = e.way_id AND aw.in_route_verte = true;
+    END IF;
+END $$;
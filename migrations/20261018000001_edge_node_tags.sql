@@ -0,0 +1,10 @@
+-- Ajoute les tags des noeuds (barrières, traversées, feux) aux edges.
+-- L'import (import.sh) remplit les colonnes; ceci couvre une base déjà importée,
+-- qui n'a pas encore de table routing_node.
+DO $$
+BEGIN
+    IF to_regclass('public.edge') IS NOT NULL THEN
+        ALTER TABLE edge ADD COLUMN IF NOT EXISTS source_node_tags jsonb;
+        ALTER TABLE edge ADD COLUMN IF NOT EXISTS target_node_tags jsonb;
+    END IF;
+END $$;
@@ -0,0 +1,6 @@
+-- Niveau de stress de la circulation (LTS) de chaque way, calculé par utils::lts::classify
+-- et enregistré au chargement du graphe de routage, pour les tuiles bike_path.
+CREATE TABLE IF NOT EXISTS way_lts (
+    way_id BIGINT PRIMARY KEY,
+    lts SMALLINT NOT NULL
+);
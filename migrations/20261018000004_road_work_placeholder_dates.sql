@@ -0,0 +1,8 @@
+-- Avant que les dates d'Info-Travaux soient lues, toutes les entraves étaient datées du
+-- 2024-04-01. Elles redeviennent sans dates (toujours en vigueur) jusqu'au prochain
+-- chargement des données de Montréal, qui remplace toute la table.
+UPDATE road_work
+SET start_date = NULL,
+    end_date = NULL
+WHERE start_date = '2024-04-01'
+    AND end_date = '2024-04-01';
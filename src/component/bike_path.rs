@@ -36,9 +36,12 @@ pub async fn bike_path_mvt(
                 ) AS geom,
                 aw.tags,
                 'cycleway' as kind,
-                false as snow
+                false as snow,
+                wl.lts
             FROM
-                all_way aw, bounds b
+                all_way aw
+                CROSS JOIN bounds b
+                LEFT JOIN way_lts wl ON wl.way_id = aw.way_id
             WHERE
                 (aw.tags->>'highway' = 'cycleway' OR
                      aw.tags->>'cycleway' = 'track' OR
@@ -63,6 +66,7 @@ pub async fn bike_path_mvt(
         ),
         all_bike_paths AS (
             SELECT
+                fw.way_id,
                 fw.geom,
                 fw.tags,
                 cs.city_name IS NOT NULL as snow,
@@ -115,9 +119,12 @@ pub async fn bike_path_mvt(
             ) AS geom,
             abp.tags,
             abp.kind,
-            abp.snow
+            abp.snow,
+            wl.lts
         FROM
-            all_bike_paths abp, bounds b
+            all_bike_paths abp
+            CROSS JOIN bounds b
+            LEFT JOIN way_lts wl ON wl.way_id = abp.way_id
         WHERE
             abp.kind IS NOT NULL AND
             -- On n'exclut que si c'est explicitement interdit en hiver
@@ -200,7 +207,8 @@ pub async fn bike_path() -> Json<JsonValue> {
                 "fields": {
                     "tags": "String",
                     "kind": "String",
-                    "snow": "Boolean"
+                    "snow": "Boolean",
+                    "lts": "Number"
                 },
                 "minzoom": 0,
                 "maxzoom": 22
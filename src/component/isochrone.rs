@@ -0,0 +1,83 @@
+use axum::{
+    debug_handler,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::db::edge::Edge;
+use crate::utils::cost::{BikeType, CostProfile, Preferences};
+use crate::utils::isochrone::{
+    isochrone_bands, parse_minutes, reachable_segments, DEFAULT_MINUTES,
+};
+use crate::utils::speed::SpeedModel;
+use crate::VeloinfoState;
+
+#[derive(Deserialize, Debug)]
+pub struct IsochroneParams {
+    allow_ferry: Option<bool>,
+    speed: Option<f64>,
+    /// Seuils en minutes au format `10,20,30`
+    minutes: Option<String>,
+}
+
+/// Zone atteignable à vélo depuis un point, en GeoJSON (une bande par seuil).
+/// `route` est le profil (`safe` ou `fast`) qui détermine les chemins suivis.
+#[debug_handler]
+pub async fn isochrone(
+    State(state): State<VeloinfoState>,
+    Path((route, lng, lat)): Path<(String, f64, f64)>,
+    params: Query<IsochroneParams>,
+) -> Response {
+    let profile = match CostProfile::new(&route, BikeType::Regular, Preferences::default()) {
+        Ok(profile) => profile,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let minutes = match &params.minutes {
+        Some(minutes) => match parse_minutes(minutes) {
+            Ok(minutes) => minutes,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        },
+        None => DEFAULT_MINUTES.to_vec(),
+    };
+    let speed_model = match SpeedModel::new(params.speed) {
+        Ok(speed_model) => speed_model,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let allow_ferry = params.allow_ferry.unwrap_or(true);
+
+    let start = match Edge::find_closest_node(&lng, &lat, &state.conn).await {
+        Ok(node) => node,
+        Err(e) => {
+            return (
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Error while fetching start node for {}, {}: {}",
+                    lng, lat, e
+                ),
+            )
+                .into_response()
+        }
+    };
+    let max_time = minutes.last().copied().unwrap_or_default() * 60.0;
+    let segments = match reachable_segments(
+        start.node_id,
+        max_time,
+        profile.h(),
+        &speed_model,
+        allow_ferry,
+        &state.conn,
+    )
+    .await
+    {
+        Ok(segments) => segments,
+        Err(e) => return (StatusCode::NOT_FOUND, e).into_response(),
+    };
+
+    (
+        [(header::CONTENT_TYPE, "application/geo+json")],
+        isochrone_bands(&segments, &minutes).to_string(),
+    )
+        .into_response()
+}
@@ -1,7 +1,10 @@
 pub mod bike_path;
 pub mod info_panel;
+pub mod isochrone;
 pub mod photo_scroll;
 pub mod point_panel;
+pub mod round_trip;
+pub mod route_export;
 pub mod route_panel;
 pub mod route_verte;
 pub mod score_circle;
@@ -9,3 +12,4 @@ pub mod score_selector;
 pub mod search;
 pub mod segment_panel;
 pub mod style;
+pub mod touring;
@@ -0,0 +1,123 @@
+use axum::{
+    debug_handler,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::component::route_panel::{from_query, CostParams};
+use crate::utils::round_trip::{
+    find_round_trips, max_candidates, DEFAULT_CANDIDATES, MAX_CANDIDATES, MAX_LOOP_DISTANCE,
+    MIN_LOOP_DISTANCE,
+};
+use crate::utils::speed::SpeedModel;
+use crate::VeloinfoState;
+
+#[derive(Deserialize, Debug)]
+pub struct RoundTripParams {
+    #[serde(flatten)]
+    cost: CostParams,
+    /// Nombre de boucles candidates à calculer, réduit pour les longues boucles
+    #[serde(default, deserialize_with = "from_query")]
+    candidates: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RoundTripResponse {
+    pub coordinates: Vec<(f64, f64)>,
+    /// Longueur réelle en mètres
+    pub length: f64,
+    /// Fraction de la longueur parcourue plus d'une fois
+    pub reused: f64,
+    /// Cap (degrés) vers lequel part la boucle
+    pub bearing: f64,
+    /// Durée estimée en secondes
+    pub duration: f64,
+}
+
+/// Boucles partant et revenant à un même point pour une distance cible en km.
+/// `route` est le profil (`safe` ou `fast`) utilisé pour chaque côté de la boucle.
+#[debug_handler]
+pub async fn round_trip(
+    State(state): State<VeloinfoState>,
+    Path((route, lng, lat, distance)): Path<(String, f64, f64, f64)>,
+    params: Query<RoundTripParams>,
+) -> Response {
+    let profile = match params.cost.cost_profile(&route) {
+        Ok(profile) => profile,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let distance = distance * 1000.0;
+    if !(MIN_LOOP_DISTANCE..=MAX_LOOP_DISTANCE).contains(&distance) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid distance {} km: must be between {} and {} km",
+                distance / 1000.0,
+                MIN_LOOP_DISTANCE / 1000.0,
+                MAX_LOOP_DISTANCE / 1000.0
+            ),
+        )
+            .into_response();
+    }
+    let candidates = params.candidates.unwrap_or(DEFAULT_CANDIDATES);
+    if !(1..=MAX_CANDIDATES).contains(&candidates) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid candidates {}: must be between 1 and {}",
+                candidates, MAX_CANDIDATES
+            ),
+        )
+            .into_response();
+    }
+    let candidates = candidates.min(max_candidates(distance));
+    let speed_model = match params
+        .cost
+        .bike()
+        .and_then(|bike| SpeedModel::for_bike(bike, params.cost.speed))
+    {
+        Ok(speed_model) => speed_model,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let allow_ferry = params.cost.allow_ferry.unwrap_or(true);
+
+    let round_trips = match find_round_trips(
+        (lng, lat),
+        distance,
+        candidates,
+        &profile,
+        allow_ferry,
+        &state.conn,
+    )
+    .await
+    {
+        Ok(round_trips) => round_trips,
+        Err(e) => return (StatusCode::NOT_FOUND, e).into_response(),
+    };
+    if round_trips.is_empty() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("No loop found from {}, {}", lng, lat),
+        )
+            .into_response();
+    }
+
+    let response: Vec<RoundTripResponse> = round_trips
+        .iter()
+        .map(|round_trip| RoundTripResponse {
+            coordinates: round_trip
+                .points
+                .iter()
+                .map(|point| (point.lng, point.lat))
+                .collect(),
+            length: round_trip.length,
+            reused: round_trip.reused,
+            bearing: round_trip.bearing,
+            duration: speed_model.route_duration(&round_trip.points),
+        })
+        .collect();
+    Json(response).into_response()
+}
@@ -0,0 +1,72 @@
+use axum::{
+    debug_handler,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::component::route_panel::{calculate_route, RouteParams};
+use crate::utils::export::{to_geojson, to_gpx};
+use crate::VeloinfoState;
+
+/// Téléchargement d'un itinéraire en GPX ou en GeoJSON.
+/// Prend les mêmes paramètres que `recalculate_route`.
+#[debug_handler]
+pub async fn route_export(
+    State(state): State<VeloinfoState>,
+    Path((route, format, start_lng, start_lat, end_lng, end_lat)): Path<(
+        String,
+        String,
+        f64,
+        f64,
+        f64,
+        f64,
+    )>,
+    route_params: Query<RouteParams>,
+) -> Response {
+    if format != "gpx" && format != "geojson" {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid export format: {}", format),
+        )
+            .into_response();
+    }
+    let profile = match route_params.cost.cost_profile(&route) {
+        Ok(profile) => profile,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let allow_ferry = route_params.cost.allow_ferry.unwrap_or(true);
+    let points = match calculate_route(
+        &profile,
+        (start_lng, start_lat),
+        (end_lng, end_lat),
+        allow_ferry,
+        &state.conn,
+    )
+    .await
+    {
+        Ok(points) => points,
+        Err(e) => return (StatusCode::NOT_FOUND, e).into_response(),
+    };
+
+    let name = format!("veloinfo-{}", route);
+    let (content_type, body) = if format == "gpx" {
+        ("application/gpx+xml", to_gpx(&points, &name))
+    } else {
+        (
+            "application/geo+json",
+            to_geojson(&points, &name).to_string(),
+        )
+    };
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", name, format),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
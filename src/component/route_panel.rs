@@ -1,11 +1,33 @@
-use crate::utils::cost::{get_h_moyen, get_h_rapid};
+use crate::db::hazard::{self, Hazard};
+use crate::db::road_work::parse_departure;
+use crate::db::utils::distance_meters;
+use crate::utils::alternative::{
+    find_alternative, find_alternative_via, Alternative, DEFAULT_MAX_SHARED,
+};
+use crate::utils::avoid::AvoidAreas;
+use crate::utils::cost::{BikeType, CostProfile, FastOrSafe, Preferences};
+use crate::utils::elevation::{get_elevation_profile, ElevationProfile};
+use crate::utils::infrastructure::InfrastructureBreakdown;
+use crate::utils::instructions::{get_instructions, Instruction};
+use crate::utils::lts::LtsBreakdown;
+use crate::utils::speed::SpeedModel;
 use askama::Template;
 use axum::{
     debug_handler,
-    extract::{ws::WebSocketUpgrade, Path, Query, State},
+    extract::{
+        ws::{WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
     response::Response,
 };
-use serde::Deserialize;
+use chrono::NaiveDate;
+use futures::future::join_all;
+use itertools::Itertools;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use sqlx::Postgres;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc as ARc;
 use tokio::join;
 
 use crate::{
@@ -13,6 +35,11 @@ use crate::{
     VeloinfoState,
 };
 
+/// Nombre maximal de points de passage acceptés par `route_via`.
+const MAX_WAYPOINTS: usize = 10;
+/// Au-delà de ce nombre d'arrêts intermédiaires, on ne teste plus toutes les permutations.
+const MAX_OPTIMIZED_STOPS: usize = 7;
+
 #[derive(Template)]
 #[template(path = "route_panel.html", escape = "none")]
 pub struct RoutePanel {
@@ -20,6 +47,13 @@ pub struct RoutePanel {
     pub error: String,
     pub ferry: bool,
     pub names: String,
+    pub legs: String,
+    pub instructions: String,
+    pub elevation: String,
+    pub durations: String,
+    pub hazards: String,
+    pub alternative: String,
+    pub profile: String,
 }
 
 impl RoutePanel {
@@ -29,13 +63,374 @@ impl RoutePanel {
             error,
             ferry: false,
             names: "[]".to_string(),
+            legs: "[]".to_string(),
+            instructions: "[]".to_string(),
+            elevation: "[]".to_string(),
+            durations: "[]".to_string(),
+            hazards: "[]".to_string(),
+            alternative: "null".to_string(),
+            profile: "null".to_string(),
+        }
+    }
+}
+
+/// Nombre ou booléen d'un champ aplati (`serde(flatten)`): les valeurs de la requête
+/// y arrivent sous forme de texte
+pub fn from_query<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| value.parse().map_err(de::Error::custom))
+        .transpose()
+}
+
+/// Paramètres du profil de coût, communs à tous les calculs d'itinéraire
+#[derive(Deserialize, Debug)]
+pub struct CostParams {
+    #[serde(default, deserialize_with = "from_query")]
+    pub allow_ferry: Option<bool>,
+    /// Vitesse de croisière en km/h pour l'estimation des durées
+    #[serde(default, deserialize_with = "from_query")]
+    pub speed: Option<f64>,
+    /// Préférences de l'usager, entre 0 et 1 (voir `cost::Preferences`)
+    #[serde(default, deserialize_with = "from_query")]
+    pub avoid_hills: Option<f64>,
+    #[serde(default, deserialize_with = "from_query")]
+    pub avoid_gravel: Option<f64>,
+    #[serde(default, deserialize_with = "from_query")]
+    pub prefer_separated: Option<f64>,
+    #[serde(default, deserialize_with = "from_query")]
+    pub tolerate_main_roads: Option<f64>,
+    /// Type de vélo: `regular` (par défaut), `ebike`, `cargo`, `road` ou `touring`
+    pub profile: Option<String>,
+    /// Polygones GeoJSON (EPSG:4326) à contourner
+    pub avoid: Option<String>,
+    /// Date ou date et heure de départ (voir `road_work::parse_departure`), aujourd'hui par défaut
+    pub departure: Option<String>,
+}
+
+impl CostParams {
+    pub fn bike(&self) -> Result<BikeType, String> {
+        BikeType::parse(self.profile.as_deref())
+    }
+
+    pub fn preferences(&self) -> Result<Preferences, String> {
+        Preferences::new(
+            self.avoid_hills,
+            self.avoid_gravel,
+            self.prefer_separated,
+            self.tolerate_main_roads,
+        )
+    }
+
+    pub fn avoid_areas(&self) -> Result<Option<ARc<AvoidAreas>>, String> {
+        parse_avoid(self.avoid.as_deref())
+    }
+
+    /// Profil de coût complet de la requête: vélo, préférences, zones à éviter et départ
+    pub fn cost_profile(&self, route: &str) -> Result<CostProfile, String> {
+        let profile = CostProfile::new(route, self.bike()?, self.preferences()?)?
+            .with_avoid(self.avoid_areas()?);
+        match &self.departure {
+            Some(departure) => Ok(profile.with_departure(parse_departure(departure)?)),
+            None => Ok(profile),
         }
     }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct RouteParams {
-    allow_ferry: Option<bool>,
+    #[serde(flatten)]
+    pub cost: CostParams,
+    /// Calcule aussi un itinéraire alternatif au sécurisé (désactivé par défaut)
+    pub alternative: Option<bool>,
+    /// Fraction maximale de l'itinéraire alternatif partagée avec l'itinéraire sécurisé
+    pub max_shared: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RouteViaParams {
+    #[serde(flatten)]
+    cost: CostParams,
+    alternative: Option<bool>,
+    max_shared: Option<f64>,
+    /// Réordonne les arrêts intermédiaires pour minimiser la distance totale
+    optimize: Option<bool>,
+}
+
+fn parse_avoid(avoid: Option<&str>) -> Result<Option<ARc<AvoidAreas>>, String> {
+    avoid
+        .map(|avoid| AvoidAreas::parse(avoid).map(ARc::new))
+        .transpose()
+}
+
+/// Profils de coût `safe` et `fast` avec les mêmes paramètres de requête
+fn safe_and_fast(safe: CostProfile) -> (CostProfile, CostProfile) {
+    let fast = CostProfile {
+        route: FastOrSafe::Fast,
+        ..safe.clone()
+    };
+    (safe, fast)
+}
+
+/// Résumé d'un tronçon entre deux points de passage consécutifs
+#[derive(Serialize, Debug, Clone)]
+pub struct Leg {
+    /// Distance en mètres
+    pub distance: f64,
+    /// Noms de rues empruntées, dans l'ordre, sans doublons consécutifs
+    pub names: Vec<String>,
+    /// Durée estimée en secondes
+    pub duration: f64,
+    /// Distance par niveau de stress de la circulation
+    pub lts: LtsBreakdown,
+    /// Distance par type d'infrastructure
+    pub infrastructure: InfrastructureBreakdown,
+}
+
+impl Leg {
+    pub fn from_points(points: &[Point], speed_model: &SpeedModel, departure: NaiveDate) -> Leg {
+        let distance = points
+            .windows(2)
+            .map(|w| distance_meters(w[0].lat, w[0].lng, w[1].lat, w[1].lng))
+            .sum();
+        let names = points
+            .iter()
+            .filter_map(|point| point.name.clone())
+            .dedup()
+            .collect();
+        Leg {
+            distance,
+            names,
+            duration: speed_model.route_duration(points),
+            lts: LtsBreakdown::from_points(points),
+            infrastructure: InfrastructureBreakdown::from_points(points, departure),
+        }
+    }
+}
+
+/// Encode du JSON pour l'insérer dans un attribut HTML.
+/// Le navigateur décode automatiquement &quot; &#39; etc. lors de getAttribute().
+fn html_attribute(json: String) -> String {
+    json.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn endpoint(lng: f64, lat: f64) -> Point {
+    Point {
+        lng,
+        lat,
+        length: 0.0,
+        way_id: 0,
+        node_id: 0,
+        ferry: false,
+        name: None,
+        edge: None,
+    }
+}
+
+/// Lit une liste de points de passage au format `lng,lat;lng,lat;...`
+pub fn parse_waypoints(waypoints: &str) -> Result<Vec<(f64, f64)>, String> {
+    let waypoints = waypoints
+        .split(';')
+        .filter(|w| !w.trim().is_empty())
+        .map(|w| {
+            let (lng, lat) = w
+                .split_once(',')
+                .ok_or_else(|| format!("Invalid waypoint: {}", w))?;
+            let lng: f64 = lng
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid longitude: {}", lng))?;
+            let lat: f64 = lat
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid latitude: {}", lat))?;
+            if !(-180.0..=180.0).contains(&lng) || !(-90.0..=90.0).contains(&lat) {
+                return Err(format!("Waypoint out of range: {}", w));
+            }
+            Ok((lng, lat))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if waypoints.len() < 2 {
+        return Err("At least two waypoints are required".to_string());
+    }
+    if waypoints.len() > MAX_WAYPOINTS {
+        return Err(format!("At most {} waypoints are allowed", MAX_WAYPOINTS));
+    }
+    Ok(waypoints)
+}
+
+/// Réordonne les arrêts intermédiaires (le départ et l'arrivée restent fixes)
+/// pour minimiser la distance à vol d'oiseau totale.
+pub fn optimize_waypoints(waypoints: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    if waypoints.len() <= 3 || waypoints.len() - 2 > MAX_OPTIMIZED_STOPS {
+        return waypoints.to_vec();
+    }
+    let first = waypoints[0];
+    let last = waypoints[waypoints.len() - 1];
+    let stops = &waypoints[1..waypoints.len() - 1];
+    let total = |order: &[&(f64, f64)]| -> f64 {
+        std::iter::once(&first)
+            .chain(order.iter().copied())
+            .chain(std::iter::once(&last))
+            .tuple_windows()
+            .map(|(a, b)| distance_meters(a.1, a.0, b.1, b.0))
+            .sum()
+    };
+    let best = stops
+        .iter()
+        .permutations(stops.len())
+        .min_by(|a, b| total(a).total_cmp(&total(b)))
+        .unwrap_or_default();
+    std::iter::once(first)
+        .chain(best.into_iter().copied())
+        .chain(std::iter::once(last))
+        .collect()
+}
+
+/// Dangers qui restent sur l'itinéraire. Une erreur n'empêche pas d'afficher l'itinéraire.
+async fn hazards(
+    points: &[Point],
+    departure: NaiveDate,
+    conn: &sqlx::Pool<Postgres>,
+) -> Vec<Hazard> {
+    hazard::along_route(points, departure, conn)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Error while fetching hazards: {}", e);
+            vec![]
+        })
+}
+
+/// `max_shared` de la requête, si l'itinéraire alternatif est demandé
+fn requested_max_shared(
+    alternative: Option<bool>,
+    max_shared: Option<f64>,
+) -> Result<Option<f64>, String> {
+    if !alternative.unwrap_or(false) {
+        return Ok(None);
+    }
+    let max_shared = max_shared.unwrap_or(DEFAULT_MAX_SHARED);
+    if !(max_shared > 0.0 && max_shared <= 1.0) {
+        return Err(format!(
+            "Invalid max_shared {}: must be between 0 and 1",
+            max_shared
+        ));
+    }
+    Ok(Some(max_shared))
+}
+
+/// Attribut `alternative` du panneau: le tracé complet de l'alternative, ou `null`
+async fn alternative_json(
+    alternative: Option<Alternative>,
+    (start_lng, start_lat): (f64, f64),
+    (end_lng, end_lat): (f64, f64),
+    speed_model: &SpeedModel,
+    departure: NaiveDate,
+    conn: &sqlx::Pool<Postgres>,
+) -> String {
+    let Some(mut alternative) = alternative else {
+        return "null".to_string();
+    };
+    alternative.points.insert(0, endpoint(start_lng, start_lat));
+    alternative.points.push(endpoint(end_lng, end_lat));
+    let points = &alternative.points;
+    let hazards = hazards(points, departure, conn).await;
+    serde_json::json!({
+        "coordinates": points.iter().map(|point| (point.lng, point.lat)).collect::<Vec<_>>(),
+        "names": points.iter().map(|point| point.name.clone()).collect::<Vec<_>>(),
+        "legs": [Leg::from_points(points, speed_model, departure)],
+        "instructions": get_instructions(points),
+        "elevation": get_elevation_profile(points),
+        "duration": speed_model.route_duration(points),
+        "hazards": hazards,
+        "shared": alternative.shared,
+        "reasons": alternative.reasons,
+        "characteristics": alternative.characteristics,
+    })
+    .to_string()
+}
+
+/// Calcule un tronçon entre chaque paire de noeuds consécutifs.
+/// Retourne None si un des tronçons est introuvable.
+async fn route_legs(
+    node_ids: &[i64],
+    profile: &CostProfile,
+    conn: &sqlx::Pool<Postgres>,
+    mut socket: Option<&mut WebSocket>,
+    allow_ferry: bool,
+) -> Option<Vec<Vec<Point>>> {
+    let mut legs = vec![];
+    for (start, end) in node_ids.iter().tuple_windows() {
+        let points = Edge::route(
+            *start,
+            *end,
+            profile,
+            conn,
+            socket.as_deref_mut(),
+            allow_ferry,
+        )
+        .await;
+        if points.is_empty() {
+            return None;
+        }
+        legs.push(points);
+    }
+    Some(legs)
+}
+
+/// Calcule un seul itinéraire selon `profile` entre deux coordonnées.
+/// Les coordonnées demandées sont ajoutées au début et à la fin du tracé.
+pub async fn calculate_route(
+    profile: &CostProfile,
+    (start_lng, start_lat): (f64, f64),
+    (end_lng, end_lat): (f64, f64),
+    allow_ferry: bool,
+    conn: &sqlx::Pool<Postgres>,
+) -> Result<Vec<Point>, String> {
+    let start = Edge::find_closest_node(&start_lng, &start_lat, conn)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error while fetching start node for {}, {}: {}",
+                start_lng, start_lat, e
+            )
+        })?;
+    let end = Edge::find_closest_node(&end_lng, &end_lat, conn)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error while fetching end node for {}, {}: {}",
+                end_lng, end_lat, e
+            )
+        })?;
+    let mut points =
+        Edge::route(start.node_id, end.node_id, profile, conn, None, allow_ferry).await;
+    if points.is_empty() {
+        return Err(format!("No route found from {start:?} to {end:?}"));
+    }
+    points.insert(0, endpoint(start_lng, start_lat));
+    points.push(endpoint(end_lng, end_lat));
+    Ok(points)
+}
+
+/// Assemble les tronçons en un seul itinéraire allant du départ à l'arrivée demandés.
+fn stitch_legs(legs: &[Vec<Point>], start: (f64, f64), end: (f64, f64)) -> Vec<Point> {
+    let mut points = vec![endpoint(start.0, start.1)];
+    for (i, leg) in legs.iter().enumerate() {
+        // Le premier point d'un tronçon est le dernier point du tronçon précédent
+        let skip = if i == 0 { 0 } else { 1 };
+        points.extend(leg.iter().skip(skip).cloned());
+    }
+    points.push(endpoint(end.0, end.1));
+    points
 }
 
 #[debug_handler]
@@ -46,7 +441,45 @@ pub async fn route(
     route_params: Query<RouteParams>,
 ) -> Response {
     ws.on_upgrade(async move |mut socket| {
-        let allow_ferry = route_params.allow_ferry.unwrap_or(true);
+        let allow_ferry = route_params.cost.allow_ferry.unwrap_or(true);
+        let speed_model = match route_params
+            .cost
+            .bike()
+            .and_then(|bike| SpeedModel::for_bike(bike, route_params.cost.speed))
+        {
+            Ok(speed_model) => speed_model,
+            Err(e) => {
+                let error_panel = RoutePanel::error(e);
+                socket
+                    .send(error_panel.render().unwrap().into())
+                    .await
+                    .unwrap();
+                return;
+            }
+        };
+        let max_shared =
+            match requested_max_shared(route_params.alternative, route_params.max_shared) {
+                Ok(max_shared) => max_shared,
+                Err(e) => {
+                    let error_panel = RoutePanel::error(e);
+                    socket
+                        .send(error_panel.render().unwrap().into())
+                        .await
+                        .unwrap();
+                    return;
+                }
+            };
+        let (safe_profile, fast_profile) = match route_params.cost.cost_profile("safe") {
+            Ok(profile) => safe_and_fast(profile),
+            Err(e) => {
+                let error_panel = RoutePanel::error(e);
+                socket
+                    .send(error_panel.render().unwrap().into())
+                    .await
+                    .unwrap();
+                return;
+            }
+        };
         let state = state.clone();
         let start = match Edge::find_closest_node(&start_lng, &start_lat, &state.conn).await {
             Ok(start) => start,
@@ -77,18 +510,18 @@ pub async fn route(
             }
         };
         let (mut points, mut points_rapide) = join!(
-            Edge::a_star_bidirectional(
+            Edge::route(
                 start.node_id,
                 end.node_id,
-                get_h_moyen(),
+                &safe_profile,
                 &state.conn,
                 Some(&mut socket),
                 allow_ferry,
             ),
-            Edge::a_star_bidirectional(
+            Edge::route(
                 start.node_id,
                 end.node_id,
-                get_h_rapid(),
+                &fast_profile,
                 &state.conn,
                 None,
                 allow_ferry,
@@ -105,48 +538,42 @@ pub async fn route(
             return;
         };
 
-        points.insert(
-            0,
-            Point {
-                lng: start_lng,
-                lat: start_lat,
-                length: 0.0,
-                way_id: 0,
-                node_id: 0,
-                ferry: false,
-                name: None,
-            },
-        );
-        points.push(Point {
-            lng: end_lng,
-            lat: end_lat,
-            length: 0.0,
-            way_id: 0,
-            node_id: 0,
-            ferry: false,
-            name: None,
-        });
-        points_rapide.insert(
-            0,
-            Point {
-                lng: start_lng,
-                lat: start_lat,
-                length: 0.0,
-                way_id: 0,
-                node_id: 0,
-                ferry: false,
-                name: None,
-            },
+        // Troisième option, réellement différente de l'itinéraire sécurisé
+        let alternative = match max_shared {
+            Some(max_shared) => {
+                find_alternative(
+                    start.node_id,
+                    end.node_id,
+                    &points,
+                    &safe_profile,
+                    max_shared,
+                    allow_ferry,
+                    &state.conn,
+                )
+                .await
+            }
+            None => None,
+        };
+
+        points.insert(0, endpoint(start_lng, start_lat));
+        points.push(endpoint(end_lng, end_lat));
+        points_rapide.insert(0, endpoint(start_lng, start_lat));
+        points_rapide.push(endpoint(end_lng, end_lat));
+
+        let (hazards_safe, hazards_fast) = join!(
+            hazards(&points, safe_profile.departure, &state.conn),
+            hazards(&points_rapide, fast_profile.departure, &state.conn)
         );
-        points_rapide.push(Point {
-            lng: end_lng,
-            lat: end_lat,
-            length: 0.0,
-            way_id: 0,
-            node_id: 0,
-            ferry: false,
-            name: None,
-        });
+
+        let alternative_json = alternative_json(
+            alternative,
+            (start_lng, start_lat),
+            (end_lng, end_lat),
+            &speed_model,
+            safe_profile.departure,
+            &state.conn,
+        )
+        .await;
 
         let edges_coordinate_safe: Vec<(f64, f64)> =
             points.iter().map(|point| (point.lng, point.lat)).collect();
@@ -156,22 +583,54 @@ pub async fn route(
             .collect();
         let names_safe: Vec<Option<String>> =
             points.iter().map(|point| point.name.clone()).collect();
-        let names_fast: Vec<Option<String>> =
-            points_rapide.iter().map(|point| point.name.clone()).collect();
+        let names_fast: Vec<Option<String>> = points_rapide
+            .iter()
+            .map(|point| point.name.clone())
+            .collect();
         let names_json = serde_json::to_string(&[names_safe, names_fast])
             .unwrap_or_else(|e| format!("Error serializing names: {}", e));
-        // HTML-encode the JSON so quotes don't break the HTML attribute.
-        // The browser automatically decodes &quot; &#39; etc. when reading getAttribute().
-        let names_html = names_json
-            .replace('&', "&amp;")
-            .replace('"', "&quot;")
-            .replace('\'', "&#39;")
-            .replace('<', "&lt;")
-            .replace('>', "&gt;");
+        let legs_json = serde_json::to_string(&[
+            vec![Leg::from_points(
+                &points,
+                &speed_model,
+                safe_profile.departure,
+            )],
+            vec![Leg::from_points(
+                &points_rapide,
+                &speed_model,
+                fast_profile.departure,
+            )],
+        ])
+        .unwrap_or_else(|e| format!("Error serializing legs: {}", e));
+        let instructions_json =
+            serde_json::to_string(&[get_instructions(&points), get_instructions(&points_rapide)])
+                .unwrap_or_else(|e| format!("Error serializing instructions: {}", e));
+        let elevation_json = serde_json::to_string(&[
+            get_elevation_profile(&points),
+            get_elevation_profile(&points_rapide),
+        ])
+        .unwrap_or_else(|e| format!("Error serializing elevation: {}", e));
+        let durations_json = serde_json::to_string(&[
+            speed_model.route_duration(&points),
+            speed_model.route_duration(&points_rapide),
+        ])
+        .unwrap_or_else(|e| format!("Error serializing durations: {}", e));
+        let hazards_json = serde_json::to_string(&[hazards_safe, hazards_fast])
+            .unwrap_or_else(|e| format!("Error serializing hazards: {}", e));
         let panel = RoutePanel {
             coordinates: serde_json::to_string(&[edges_coordinate_safe, edges_coordinate_fast])
                 .unwrap_or_else(|e| format!("Error serializing edges: {}", e)),
-            names: names_html,
+            names: html_attribute(names_json),
+            legs: html_attribute(legs_json),
+            instructions: html_attribute(instructions_json),
+            elevation: html_attribute(elevation_json),
+            durations: html_attribute(durations_json),
+            hazards: html_attribute(hazards_json),
+            alternative: html_attribute(alternative_json),
+            profile: html_attribute(
+                serde_json::to_string(&[safe_profile, fast_profile])
+                    .unwrap_or_else(|e| format!("Error serializing profile: {}", e)),
+            ),
             error: "".to_string(),
             ferry: points.iter().any(|point| point.ferry),
         }
@@ -181,107 +640,277 @@ pub async fn route(
     })
 }
 
+/// Itinéraire passant par une liste ordonnée de points de passage.
+/// Les points sont donnés sous la forme `lng,lat;lng,lat;...`
 #[debug_handler]
-pub async fn recalculate_route(
+pub async fn route_via(
     ws: WebSocketUpgrade,
     State(state): State<VeloinfoState>,
-    Path((route, start_lng, start_lat, end_lng, end_lat)): Path<(
-        String,
-        f64,
-        f64,
-        f64,
-        f64,
-    )>,
-    route_params: Query<RouteParams>,
+    Path(waypoints): Path<String>,
+    route_params: Query<RouteViaParams>,
 ) -> Response {
     ws.on_upgrade(async move |mut socket| {
-        let allow_ferry = route_params.allow_ferry.unwrap_or(true);
-        let state = state.clone();
-        let start = match Edge::find_closest_node(&start_lng, &start_lat, &state.conn).await {
-            Ok(start) => start,
+        let allow_ferry = route_params.cost.allow_ferry.unwrap_or(true);
+        let speed_model = match route_params
+            .cost
+            .bike()
+            .and_then(|bike| SpeedModel::for_bike(bike, route_params.cost.speed))
+        {
+            Ok(speed_model) => speed_model,
             Err(e) => {
-                let error_panel = format!(
-                    "Error while fetching start node for {}, {}: {}",
-                    start_lng, start_lat, e
-                );
-                socket.send(error_panel.into()).await.unwrap();
+                let error_panel = RoutePanel::error(e);
+                socket
+                    .send(error_panel.render().unwrap().into())
+                    .await
+                    .unwrap();
                 return;
             }
         };
-        let end = match Edge::find_closest_node(&end_lng, &end_lat, &state.conn).await {
-            Ok(end) => end,
+        let max_shared =
+            match requested_max_shared(route_params.alternative, route_params.max_shared) {
+                Ok(max_shared) => max_shared,
+                Err(e) => {
+                    let error_panel = RoutePanel::error(e);
+                    socket
+                        .send(error_panel.render().unwrap().into())
+                        .await
+                        .unwrap();
+                    return;
+                }
+            };
+        let (safe_profile, fast_profile) = match route_params.cost.cost_profile("safe") {
+            Ok(profile) => safe_and_fast(profile),
             Err(e) => {
-                let error_panel = format!(
-                    "Error while fetching start node for {}, {}: {}",
-                    start_lng, start_lat, e
-                );
-                socket.send(error_panel.into()).await.unwrap();
+                let error_panel = RoutePanel::error(e);
+                socket
+                    .send(error_panel.render().unwrap().into())
+                    .await
+                    .unwrap();
                 return;
             }
         };
-        let mut points = match route.as_str() {
-            "safe" => Edge::a_star_bidirectional(
-                start.node_id,
-                end.node_id,
-                get_h_moyen(),
-                &state.conn,
-                None,
-                allow_ferry,
-            ),
-            "fast" => Edge::a_star_bidirectional(
-                start.node_id,
-                end.node_id,
-                get_h_rapid(),
+        let waypoints = match parse_waypoints(&waypoints) {
+            Ok(waypoints) => waypoints,
+            Err(e) => {
+                let error_panel = RoutePanel::error(e);
+                socket
+                    .send(error_panel.render().unwrap().into())
+                    .await
+                    .unwrap();
+                return;
+            }
+        };
+        let waypoints = if route_params.optimize.unwrap_or(false) {
+            optimize_waypoints(&waypoints)
+        } else {
+            waypoints
+        };
+
+        let nodes = join_all(
+            waypoints
+                .iter()
+                .map(|(lng, lat)| Edge::find_closest_node(lng, lat, &state.conn)),
+        )
+        .await;
+        let mut node_ids = vec![];
+        for (node, (lng, lat)) in nodes.into_iter().zip(waypoints.iter()) {
+            match node {
+                Ok(node) => node_ids.push(node.node_id),
+                Err(e) => {
+                    let error_panel = RoutePanel::error(format!(
+                        "Error while fetching node for {}, {}: {}",
+                        lat, lng, e
+                    ));
+                    socket
+                        .send(error_panel.render().unwrap().into())
+                        .await
+                        .unwrap();
+                    return;
+                }
+            }
+        }
+
+        let (legs_safe, legs_fast) = join!(
+            route_legs(
+                &node_ids,
+                &safe_profile,
                 &state.conn,
-                None,
+                Some(&mut socket),
                 allow_ferry,
             ),
+            route_legs(&node_ids, &fast_profile, &state.conn, None, allow_ferry)
+        );
+        let (legs_safe, legs_fast) = match (legs_safe, legs_fast) {
+            (Some(legs_safe), Some(legs_fast)) => (legs_safe, legs_fast),
             _ => {
-                let error_panel = format!("Invalid route type: {}", route);
-                socket.send(error_panel.into()).await.unwrap();
+                let error_panel =
+                    RoutePanel::error(format!("No route found through {waypoints:?}"));
+                socket
+                    .send(error_panel.render().unwrap().into())
+                    .await
+                    .unwrap();
                 return;
             }
-        }
-        .await;
+        };
 
-        if points.len() == 0 {
-            let error_panel =
-                RoutePanel::error(format!("No route found from {start:?} to {end:?}"));
-            socket
-                .send(error_panel.render().unwrap().into())
+        let alternative = match max_shared {
+            Some(max_shared) => {
+                find_alternative_via(
+                    &node_ids,
+                    &legs_safe,
+                    &safe_profile,
+                    max_shared,
+                    allow_ferry,
+                    &state.conn,
+                )
                 .await
-                .unwrap();
-            return;
+            }
+            None => None,
         };
 
-        points.insert(
-            0,
-            Point {
-                lng: start_lng,
-                lat: start_lat,
-                length: 0.0,
-                way_id: 0,
-                node_id: 0,
-                ferry: false,
-                name: None,
-            },
+        let start = waypoints[0];
+        let end = waypoints[waypoints.len() - 1];
+        let points = stitch_legs(&legs_safe, start, end);
+        let points_rapide = stitch_legs(&legs_fast, start, end);
+
+        let coordinates: Vec<Vec<(f64, f64)>> = [&points, &points_rapide]
+            .iter()
+            .map(|points| points.iter().map(|point| (point.lng, point.lat)).collect())
+            .collect();
+        let names: Vec<Vec<Option<String>>> = [&points, &points_rapide]
+            .iter()
+            .map(|points| points.iter().map(|point| point.name.clone()).collect())
+            .collect();
+        let legs: Vec<Vec<Leg>> = [&legs_safe, &legs_fast]
+            .iter()
+            .map(|legs| {
+                legs.iter()
+                    .map(|leg| Leg::from_points(leg, &speed_model, safe_profile.departure))
+                    .collect()
+            })
+            .collect();
+        let durations: Vec<f64> = [&points, &points_rapide]
+            .iter()
+            .map(|points| speed_model.route_duration(points))
+            .collect();
+        let instructions: Vec<Vec<Instruction>> = [&points, &points_rapide]
+            .iter()
+            .map(|points| get_instructions(points))
+            .collect();
+        let elevation: Vec<ElevationProfile> = [&points, &points_rapide]
+            .iter()
+            .map(|points| get_elevation_profile(points))
+            .collect();
+        let alternative_json = alternative_json(
+            alternative,
+            start,
+            end,
+            &speed_model,
+            safe_profile.departure,
+            &state.conn,
+        )
+        .await;
+        let (hazards_safe, hazards_fast) = join!(
+            hazards(&points, safe_profile.departure, &state.conn),
+            hazards(&points_rapide, fast_profile.departure, &state.conn)
         );
-        points.push(Point {
-            lng: end_lng,
-            lat: end_lat,
-            length: 0.0,
-            way_id: 0,
-            node_id: 0,
-            ferry: false,
-            name: None,
-        });
+        let panel = RoutePanel {
+            coordinates: serde_json::to_string(&coordinates)
+                .unwrap_or_else(|e| format!("Error serializing edges: {}", e)),
+            names: html_attribute(
+                serde_json::to_string(&names)
+                    .unwrap_or_else(|e| format!("Error serializing names: {}", e)),
+            ),
+            legs: html_attribute(
+                serde_json::to_string(&legs)
+                    .unwrap_or_else(|e| format!("Error serializing legs: {}", e)),
+            ),
+            instructions: html_attribute(
+                serde_json::to_string(&instructions)
+                    .unwrap_or_else(|e| format!("Error serializing instructions: {}", e)),
+            ),
+            elevation: html_attribute(
+                serde_json::to_string(&elevation)
+                    .unwrap_or_else(|e| format!("Error serializing elevation: {}", e)),
+            ),
+            durations: html_attribute(
+                serde_json::to_string(&durations)
+                    .unwrap_or_else(|e| format!("Error serializing durations: {}", e)),
+            ),
+            hazards: html_attribute(
+                serde_json::to_string(&[hazards_safe, hazards_fast])
+                    .unwrap_or_else(|e| format!("Error serializing hazards: {}", e)),
+            ),
+            alternative: html_attribute(alternative_json),
+            profile: html_attribute(
+                serde_json::to_string(&[safe_profile, fast_profile])
+                    .unwrap_or_else(|e| format!("Error serializing profile: {}", e)),
+            ),
+            error: "".to_string(),
+            ferry: points.iter().any(|point| point.ferry),
+        }
+        .render()
+        .unwrap();
+        socket.send(panel.into()).await.unwrap();
+    })
+}
+
+#[debug_handler]
+pub async fn recalculate_route(
+    ws: WebSocketUpgrade,
+    State(state): State<VeloinfoState>,
+    Path((route, start_lng, start_lat, end_lng, end_lat)): Path<(String, f64, f64, f64, f64)>,
+    route_params: Query<RouteParams>,
+) -> Response {
+    ws.on_upgrade(async move |mut socket| {
+        let allow_ferry = route_params.cost.allow_ferry.unwrap_or(true);
+        let speed_model = match route_params
+            .cost
+            .bike()
+            .and_then(|bike| SpeedModel::for_bike(bike, route_params.cost.speed))
+        {
+            Ok(speed_model) => speed_model,
+            Err(e) => {
+                socket.send(e.into()).await.unwrap();
+                return;
+            }
+        };
+        let profile = match route_params.cost.cost_profile(&route) {
+            Ok(profile) => profile,
+            Err(e) => {
+                socket.send(e.into()).await.unwrap();
+                return;
+            }
+        };
+        let points = match calculate_route(
+            &profile,
+            (start_lng, start_lat),
+            (end_lng, end_lat),
+            allow_ferry,
+            &state.conn,
+        )
+        .await
+        {
+            Ok(points) => points,
+            Err(e) => {
+                socket.send(e.into()).await.unwrap();
+                return;
+            }
+        };
+        let hazards = hazards(&points, profile.departure, &state.conn).await;
         let edges_coordinate: Vec<(f64, f64)> =
             points.iter().map(|point| (point.lng, point.lat)).collect();
-        let names: Vec<Option<String>> =
-            points.iter().map(|point| point.name.clone()).collect();
+        let names: Vec<Option<String>> = points.iter().map(|point| point.name.clone()).collect();
         let json = match serde_json::to_string(&serde_json::json!({
             "coordinates": edges_coordinate,
             "names": names,
+            "instructions": get_instructions(&points),
+            "elevation": get_elevation_profile(&points),
+            "duration": speed_model.route_duration(&points),
+            "infrastructure": InfrastructureBreakdown::from_points(&points, profile.departure),
+            "lts": LtsBreakdown::from_points(&points),
+            "hazards": hazards,
+            "profile": profile,
         })) {
             Ok(json) => json,
             Err(e) => {
@@ -292,9 +921,64 @@ pub async fn recalculate_route(
                 return;
             }
         };
-        socket
-            .send(json.into())
-            .await
-            .unwrap();
+        socket.send(json.into()).await.unwrap();
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_waypoints() {
+        let waypoints = parse_waypoints("-73.56,45.50;-73.60,45.52;-73.62,45.55").unwrap();
+        assert_eq!(waypoints.len(), 3);
+        assert_eq!(waypoints[1], (-73.60, 45.52));
+
+        assert!(parse_waypoints("-73.56,45.50").is_err());
+        assert!(parse_waypoints("-73.56,45.50;abc,45.52").is_err());
+        assert!(parse_waypoints("-73.56,45.50;-73.60,145.52").is_err());
+    }
+
+    #[test]
+    fn test_route_via_params() {
+        let uri: axum::http::Uri =
+            "/route_via?allow_ferry=false&speed=22.5&avoid_hills=0.8&profile=ebike&optimize=true&max_shared=0.5"
+                .parse()
+                .unwrap();
+        let Query(params) = Query::<RouteViaParams>::try_from_uri(&uri).unwrap();
+        assert_eq!(params.cost.allow_ferry, Some(false));
+        assert_eq!(params.cost.speed, Some(22.5));
+        assert_eq!(params.cost.avoid_hills, Some(0.8));
+        assert_eq!(params.cost.avoid_gravel, None);
+        assert_eq!(params.cost.bike(), Ok(BikeType::Ebike));
+        assert_eq!(params.optimize, Some(true));
+        assert_eq!(params.max_shared, Some(0.5));
+
+        let uri: axum::http::Uri = "/route?speed=fast".parse().unwrap();
+        assert!(Query::<RouteParams>::try_from_uri(&uri).is_err());
+    }
+
+    #[test]
+    fn test_optimize_waypoints() {
+        // Départ et arrivée fixes, les arrêts sont donnés dans le désordre
+        let waypoints = vec![
+            (-73.0, 45.0),
+            (-73.0, 45.3),
+            (-73.0, 45.1),
+            (-73.0, 45.2),
+            (-73.0, 45.4),
+        ];
+        let optimized = optimize_waypoints(&waypoints);
+        assert_eq!(
+            optimized,
+            vec![
+                (-73.0, 45.0),
+                (-73.0, 45.1),
+                (-73.0, 45.2),
+                (-73.0, 45.3),
+                (-73.0, 45.4),
+            ]
+        );
+    }
+}
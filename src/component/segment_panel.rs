@@ -277,7 +277,7 @@ pub async fn segment_panel_post(
 
     // Vider le cache des EdgePoint pour les nodes intersectant ce report
     let nodes = Report::get_intersecting_nodes(id, &state.conn).await.unwrap_or_default();
-    Edge::clear_nodes_cache(nodes).await;
+    Edge::clear_nodes_cache(nodes, &state.conn).await;
 
     // Retourner le segment avec la géométrie sauvegardée
     let geom_json_for_response = geom_json.clone();
@@ -595,7 +595,7 @@ pub async fn toggle_report(
                 Ok(()) => {
                     // Vider le cache des EdgePoint pour les nodes intersectant ce report
                     let nodes = Report::get_intersecting_nodes(id, &state.conn).await.unwrap_or_default();
-                    Edge::clear_nodes_cache(nodes).await;
+                    Edge::clear_nodes_cache(nodes, &state.conn).await;
                     Json(json!({ "success": true, "enabled": new_enabled }))
                 }
                 Err(e) => {
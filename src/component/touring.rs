@@ -0,0 +1,78 @@
+use axum::{
+    debug_handler,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::component::route_panel::calculate_route;
+use crate::utils::cost::{BikeType, CostProfile, Preferences};
+use crate::utils::speed::SpeedModel;
+use crate::utils::touring::{
+    cumulative_distances, split_stages, DEFAULT_STAGE_LENGTH, MAX_STAGE_LENGTH, MIN_STAGE_LENGTH,
+};
+use crate::VeloinfoState;
+
+#[derive(Deserialize, Debug)]
+pub struct TouringParams {
+    allow_ferry: Option<bool>,
+    speed: Option<f64>,
+    /// Longueur visée d'une étape quotidienne en km
+    stage_length: Option<f64>,
+}
+
+/// Itinéraire de cyclotourisme privilégiant la Route Verte, découpé en étapes quotidiennes.
+#[debug_handler]
+pub async fn touring(
+    State(state): State<VeloinfoState>,
+    Path((start_lng, start_lat, end_lng, end_lat)): Path<(f64, f64, f64, f64)>,
+    params: Query<TouringParams>,
+) -> Response {
+    let stage_length = params
+        .stage_length
+        .map(|km| km * 1000.0)
+        .unwrap_or(DEFAULT_STAGE_LENGTH);
+    if !(MIN_STAGE_LENGTH..=MAX_STAGE_LENGTH).contains(&stage_length) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid stage_length {} km: must be between {} and {} km",
+                stage_length / 1000.0,
+                MIN_STAGE_LENGTH / 1000.0,
+                MAX_STAGE_LENGTH / 1000.0
+            ),
+        )
+            .into_response();
+    }
+    let speed_model = match SpeedModel::for_bike(BikeType::Touring, params.speed) {
+        Ok(speed_model) => speed_model,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let profile = CostProfile::new("safe", BikeType::Touring, Preferences::default()).unwrap();
+    let allow_ferry = params.allow_ferry.unwrap_or(true);
+
+    let points = match calculate_route(
+        &profile,
+        (start_lng, start_lat),
+        (end_lng, end_lat),
+        allow_ferry,
+        &state.conn,
+    )
+    .await
+    {
+        Ok(points) => points,
+        Err(e) => return (StatusCode::NOT_FOUND, e).into_response(),
+    };
+    let stages = split_stages(&points, stage_length, &speed_model, &state.conn).await;
+
+    Json(json!({
+        "coordinates": points.iter().map(|point| (point.lng, point.lat)).collect::<Vec<_>>(),
+        "distance": cumulative_distances(&points).last().copied().unwrap_or_default(),
+        "duration": speed_model.route_duration(&points),
+        "stages": stages,
+    }))
+    .into_response()
+}
@@ -0,0 +1,629 @@
+//! Hiérarchie de contraction personnalisable (CCH) pour les longs trajets.
+//! L'ordre des noeuds (dissection emboîtée géographique) et les raccourcis ne dépendent que
+//! de la topologie du graphe: ils sont calculés une seule fois par chargement du graphe.
+//! Les coûts sont ensuite « personnalisés » pour chaque profil en un passage sur les triangles,
+//! ce qui suit les signalements, entraves et la neige sans recontracter le graphe.
+//! Les requêtes avec zones à éviter ou poids personnalisés restent sur le A* bidirectionnel.
+//! La hiérarchie est basée sur les noeuds: le coût des intersections (`H::turn_cost`)
+//! n'y entre pas, ce qui compte peu sur un long trajet.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Arc as ARc, Mutex, RwLock};
+
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use tokio::sync::OnceCell;
+
+use super::edge::EdgePoint;
+use super::graph::Graph;
+use super::turn_restriction;
+use super::utils::{distance_meters, Score};
+use crate::utils::cost::{CostProfile, FastOrSafe, H};
+
+/// Nombre maximal de noeuds d'une cellule de la dissection, ordonnée sans séparateur
+const LEAF_SIZE: usize = 64;
+/// Distance à vol d'oiseau en mètres à partir de laquelle on passe par la hiérarchie
+pub const LONG_DISTANCE: f64 = 15000.0;
+/// Nombre de profils personnalisés gardés en mémoire
+const MAX_METRICS: usize = 8;
+
+lazy_static! {
+    static ref CCH: RwLock<Option<ARc<Cch>>> = RwLock::new(None);
+}
+
+/// Ce qui détermine le coût des arcs d'un profil par défaut
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MetricKey {
+    route: FastOrSafe,
+    allow_ferry: bool,
+    departure: NaiveDate,
+}
+
+/// Provenance du coût d'un arc de la hiérarchie, pour le dérouler en arcs du graphe
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Via {
+    None,
+    /// Arc du graphe
+    Arc(u32),
+    /// Raccourci passant par le noeud de ce rang
+    Node(u32),
+}
+
+/// Coûts personnalisés des arcs de la hiérarchie
+pub struct Metric {
+    /// Du rang bas vers le rang haut
+    up: Vec<f64>,
+    /// Du rang haut vers le rang bas
+    down: Vec<f64>,
+    up_via: Vec<Via>,
+    down_via: Vec<Via>,
+}
+
+/// Metric en cours de calcul ou déjà calculée pour un profil
+type MetricCell = ARc<OnceCell<ARc<Metric>>>;
+
+pub struct Cch {
+    graph: ARc<Graph>,
+    /// Longitude et latitude de chaque noeud du graphe
+    coords: Vec<(f64, f64)>,
+    /// Rang de chaque noeud du graphe
+    rank: Vec<u32>,
+    /// Arcs montants en CSR par rang: ceux du rang `r` mènent aux rangs
+    /// `up_heads[up_offsets[r]..up_offsets[r + 1]]`, triés
+    up_offsets: Vec<u32>,
+    up_heads: Vec<u32>,
+    /// Rang bas de chaque arc montant
+    up_tails: Vec<u32>,
+    /// Coûts personnalisés par profil, calculés une seule fois même pour des requêtes simultanées
+    metrics: Mutex<Vec<(MetricKey, MetricCell)>>,
+}
+
+/// Ordre des noeuds par dissection emboîtée: chaque cellule est coupée en deux à la médiane
+/// de son axe le plus étendu, et les noeuds qui touchent l'autre moitié (le séparateur)
+/// sont classés après les deux moitiés.
+fn dissect(
+    mut nodes: Vec<u32>,
+    coords: &[(f64, f64)],
+    neighbors: &[Vec<u32>],
+    in_right: &mut [bool],
+    order: &mut Vec<u32>,
+) {
+    if nodes.len() <= LEAF_SIZE {
+        order.extend(nodes);
+        return;
+    }
+    let (mut min_lon, mut max_lon, mut min_lat, mut max_lat) = (
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+    );
+    for node in nodes.iter() {
+        let (lon, lat) = coords[*node as usize];
+        min_lon = min_lon.min(lon);
+        max_lon = max_lon.max(lon);
+        min_lat = min_lat.min(lat);
+        max_lat = max_lat.max(lat);
+    }
+    let lon_extent = (max_lon - min_lon) * ((min_lat + max_lat) / 2.0).to_radians().cos();
+    if lon_extent > max_lat - min_lat {
+        nodes.sort_by(|a, b| coords[*a as usize].0.total_cmp(&coords[*b as usize].0));
+    } else {
+        nodes.sort_by(|a, b| coords[*a as usize].1.total_cmp(&coords[*b as usize].1));
+    }
+    let right = nodes.split_off(nodes.len() / 2);
+    for node in right.iter() {
+        in_right[*node as usize] = true;
+    }
+    let (separator, left): (Vec<u32>, Vec<u32>) = nodes.into_iter().partition(|node| {
+        neighbors[*node as usize]
+            .iter()
+            .any(|neighbor| in_right[*neighbor as usize])
+    });
+    for node in right.iter() {
+        in_right[*node as usize] = false;
+    }
+    dissect(left, coords, neighbors, in_right, order);
+    dissect(right, coords, neighbors, in_right, order);
+    order.extend(separator);
+}
+
+impl Cch {
+    /// Ordonne et contracte le graphe. Aucun coût n'est calculé ici.
+    pub fn build(graph: ARc<Graph>) -> Cch {
+        let node_count = graph.node_count();
+        let coords = graph.node_coords();
+        let arcs: Vec<(u32, u32)> = (0..graph.arc_count() as u32)
+            .filter_map(|arc| graph.arc_nodes(arc))
+            .collect();
+
+        let mut neighbors = vec![vec![]; node_count];
+        for (tail, head) in arcs.iter() {
+            neighbors[*tail as usize].push(*head);
+        }
+        let mut order = Vec::with_capacity(node_count);
+        dissect(
+            (0..node_count as u32).collect(),
+            &coords,
+            &neighbors,
+            &mut vec![false; node_count],
+            &mut order,
+        );
+        drop(neighbors);
+        let mut rank = vec![0u32; node_count];
+        for (r, node) in order.iter().enumerate() {
+            rank[*node as usize] = r as u32;
+        }
+
+        // Élimination dans l'ordre des rangs: les voisins plus hauts d'un noeud sont reliés
+        // entre eux, en passant le reste de la liste à son voisin le plus bas.
+        let mut up: Vec<Vec<u32>> = vec![vec![]; node_count];
+        for (tail, head) in arcs {
+            let (a, b) = (rank[tail as usize], rank[head as usize]);
+            up[a.min(b) as usize].push(a.max(b));
+        }
+        for r in 0..node_count {
+            let mut heads = std::mem::take(&mut up[r]);
+            heads.sort_unstable();
+            heads.dedup();
+            if let Some((parent, rest)) = heads.split_first() {
+                up[*parent as usize].extend_from_slice(rest);
+            }
+            up[r] = heads;
+        }
+
+        let mut up_offsets = Vec::with_capacity(node_count + 1);
+        let mut up_heads = vec![];
+        let mut up_tails = vec![];
+        up_offsets.push(0);
+        for (r, heads) in up.into_iter().enumerate() {
+            up_tails.extend(std::iter::repeat_n(r as u32, heads.len()));
+            up_heads.extend(heads);
+            up_offsets.push(up_heads.len() as u32);
+        }
+
+        Cch {
+            graph,
+            coords,
+            rank,
+            up_offsets,
+            up_heads,
+            up_tails,
+            metrics: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn shortcut_count(&self) -> usize {
+        self.up_heads.len()
+    }
+
+    fn up_range(&self, r: u32) -> std::ops::Range<usize> {
+        self.up_offsets[r as usize] as usize..self.up_offsets[r as usize + 1] as usize
+    }
+
+    /// Arc montant entre les rangs `low` et `high`
+    fn find(&self, low: u32, high: u32) -> Option<usize> {
+        let range = self.up_range(low);
+        let start = range.start;
+        self.up_heads[range]
+            .binary_search(&high)
+            .ok()
+            .map(|i| start + i)
+    }
+
+    /// Coûts de tous les arcs de la hiérarchie selon `h`
+    pub fn customize(&self, h: &dyn H, allow_ferry: bool) -> Metric {
+        let count = self.up_heads.len();
+        let mut metric = Metric {
+            up: vec![f64::INFINITY; count],
+            down: vec![f64::INFINITY; count],
+            up_via: vec![Via::None; count],
+            down_via: vec![Via::None; count],
+        };
+        let overlay = self.graph.overlay();
+        for arc in 0..self.graph.arc_count() as u32 {
+            let Some((tail, head)) = self.graph.arc_nodes(arc) else {
+                continue;
+            };
+            let edge = self.graph.arc(arc, &overlay);
+            let cost = edge.length * h.get_cost(&edge, allow_ferry);
+            let (tail, head) = (self.rank[tail as usize], self.rank[head as usize]);
+            let (i, costs, vias) = if tail < head {
+                (self.find(tail, head), &mut metric.up, &mut metric.up_via)
+            } else {
+                (
+                    self.find(head, tail),
+                    &mut metric.down,
+                    &mut metric.down_via,
+                )
+            };
+            let i = i.expect("arc missing from hierarchy");
+            if cost < costs[i] {
+                costs[i] = cost;
+                vias[i] = Via::Arc(arc);
+            }
+        }
+
+        // Triangles inférieurs, du rang le plus bas au plus haut: les arcs d'un noeud
+        // sont définitifs quand on le traite.
+        for x in 0..self.rank.len() as u32 {
+            let range = self.up_range(x);
+            for i in range.clone() {
+                for j in i + 1..range.end {
+                    let (u, v) = (self.up_heads[i], self.up_heads[j]);
+                    let k = self.find(u, v).expect("missing shortcut");
+                    let cost = metric.down[i] + metric.up[j];
+                    if cost < metric.up[k] {
+                        metric.up[k] = cost;
+                        metric.up_via[k] = Via::Node(x);
+                    }
+                    let cost = metric.down[j] + metric.up[i];
+                    if cost < metric.down[k] {
+                        metric.down[k] = cost;
+                        metric.down_via[k] = Via::Node(x);
+                    }
+                }
+            }
+        }
+        metric
+    }
+
+    /// Place des coûts personnalisés du profil `key`, vide tant qu'ils ne sont pas calculés
+    fn metric_cell(&self, key: MetricKey) -> MetricCell {
+        let mut metrics = self.metrics.lock().unwrap();
+        if let Some((_, cell)) = metrics.iter().find(|(k, _)| *k == key) {
+            return cell.clone();
+        }
+        if metrics.len() >= MAX_METRICS {
+            metrics.remove(0);
+        }
+        let cell = ARc::new(OnceCell::new());
+        metrics.push((key, cell.clone()));
+        cell
+    }
+
+    pub fn clear_metrics(&self) {
+        self.metrics.lock().unwrap().clear();
+    }
+
+    /// Arcs du graphe du plus court chemin de `start` à `end` (index de noeuds)
+    pub fn shortest_path(&self, metric: &Metric, start: u32, end: u32) -> Option<Vec<u32>> {
+        let (start, end) = (self.rank[start as usize], self.rank[end as usize]);
+        if start == end {
+            return Some(vec![]);
+        }
+        // Recherche montante depuis chaque bout: rang -> (coût, arc montant d'arrivée)
+        let mut dist_fwd: HashMap<u32, (f64, usize)> = HashMap::new();
+        let mut dist_bwd: HashMap<u32, (f64, usize)> = HashMap::new();
+        let mut open_fwd = BinaryHeap::new();
+        let mut open_bwd = BinaryHeap::new();
+        dist_fwd.insert(start, (0.0, usize::MAX));
+        dist_bwd.insert(end, (0.0, usize::MAX));
+        open_fwd.push((Reverse(Score(0.0)), start));
+        open_bwd.push((Reverse(Score(0.0)), end));
+        let mut best = f64::INFINITY;
+        let mut meeting = None;
+
+        loop {
+            let min_fwd = open_fwd.peek().map_or(f64::INFINITY, |(Reverse(s), _)| s.0);
+            let min_bwd = open_bwd.peek().map_or(f64::INFINITY, |(Reverse(s), _)| s.0);
+            if min_fwd >= best && min_bwd >= best {
+                break;
+            }
+            let (open, dist, other, costs) = if min_fwd <= min_bwd {
+                (&mut open_fwd, &mut dist_fwd, &dist_bwd, &metric.up)
+            } else {
+                (&mut open_bwd, &mut dist_bwd, &dist_fwd, &metric.down)
+            };
+            let Some((Reverse(Score(cost)), r)) = open.pop() else {
+                break;
+            };
+            if cost > dist[&r].0 {
+                continue;
+            }
+            if let Some((other_cost, _)) = other.get(&r) {
+                if cost + other_cost < best {
+                    best = cost + other_cost;
+                    meeting = Some(r);
+                }
+            }
+            for i in self.up_range(r) {
+                let next = self.up_heads[i];
+                let next_cost = cost + costs[i];
+                if next_cost < dist.get(&next).map_or(f64::INFINITY, |(c, _)| *c) {
+                    dist.insert(next, (next_cost, i));
+                    open.push((Reverse(Score(next_cost)), next));
+                }
+            }
+        }
+        let meeting = meeting?;
+
+        // Arcs montants de start au point de rencontre, puis descendants jusqu'à end
+        let mut shortcuts = vec![];
+        let mut r = meeting;
+        while let Some((_, i)) = dist_fwd.get(&r).filter(|(_, i)| *i != usize::MAX) {
+            shortcuts.push((*i, true));
+            r = self.up_tails[*i];
+        }
+        shortcuts.reverse();
+        let mut r = meeting;
+        while let Some((_, i)) = dist_bwd.get(&r).filter(|(_, i)| *i != usize::MAX) {
+            shortcuts.push((*i, false));
+            r = self.up_tails[*i];
+        }
+
+        let mut arcs = vec![];
+        for (i, up) in shortcuts {
+            self.unpack(metric, i, up, &mut arcs);
+        }
+        Some(arcs)
+    }
+
+    /// Déroule un arc de la hiérarchie en arcs du graphe, dans l'ordre de parcours
+    fn unpack(&self, metric: &Metric, i: usize, up: bool, arcs: &mut Vec<u32>) {
+        let mut stack = vec![(i, up)];
+        while let Some((i, up)) = stack.pop() {
+            let via = if up {
+                metric.up_via[i]
+            } else {
+                metric.down_via[i]
+            };
+            let (low, high) = (self.up_tails[i], self.up_heads[i]);
+            match via {
+                Via::Arc(arc) => arcs.push(arc),
+                Via::Node(x) => {
+                    let to_low = self.find(x, low).expect("missing shortcut");
+                    let to_high = self.find(x, high).expect("missing shortcut");
+                    // Empilés à l'envers: low -> x -> high ou high -> x -> low
+                    if up {
+                        stack.push((to_high, true));
+                        stack.push((to_low, false));
+                    } else {
+                        stack.push((to_low, true));
+                        stack.push((to_high, false));
+                    }
+                }
+                Via::None => unreachable!("unpacking an arc without cost"),
+            }
+        }
+    }
+}
+
+/// Hiérarchie prête, s'il y en a une
+pub fn current() -> Option<ARc<Cch>> {
+    CCH.read().unwrap().clone()
+}
+
+/// Contracte `graph` et remplace la hiérarchie courante, qui reste utilisée pendant le calcul.
+/// Les profils sûr et rapide du jour sont personnalisés d'avance.
+pub async fn rebuild(graph: ARc<Graph>) {
+    let cch = match tokio::task::spawn_blocking(move || Cch::build(graph)).await {
+        Ok(cch) => ARc::new(cch),
+        Err(e) => {
+            eprintln!("Error while building contraction hierarchy: {}", e);
+            return;
+        }
+    };
+    for route in ["safe", "fast"] {
+        if let Ok(profile) = CostProfile::new(route, Default::default(), Default::default()) {
+            metric(&cch, metric_key(&profile, true), &profile).await;
+        }
+    }
+    println!("Contraction hierarchy built: {} arcs", cch.shortcut_count());
+    *CCH.write().unwrap() = Some(cch);
+}
+
+/// Oublie les coûts personnalisés après un changement de l'état dynamique du graphe
+pub fn clear_metrics() {
+    if let Some(cch) = current() {
+        cch.clear_metrics();
+    }
+}
+
+/// Coûts personnalisés pour un profil par défaut, calculés au premier usage.
+/// Les requêtes simultanées pour un même profil attendent le calcul de la première.
+/// None si le calcul a échoué.
+async fn metric(cch: &ARc<Cch>, key: MetricKey, profile: &CostProfile) -> Option<ARc<Metric>> {
+    cch.metric_cell(key)
+        .get_or_try_init(|| {
+            let cch = cch.clone();
+            let profile = profile.clone();
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    ARc::new(cch.customize(profile.h().as_ref(), key.allow_ferry))
+                })
+                .await
+            }
+        })
+        .await
+        .ok()
+        .cloned()
+}
+
+fn metric_key(profile: &CostProfile, allow_ferry: bool) -> MetricKey {
+    MetricKey {
+        route: profile.route,
+        allow_ferry,
+        departure: profile.departure,
+    }
+}
+
+/// Plus court chemin par la hiérarchie, sous la même forme que `a_star_bidirectional`:
+/// le premier arc arrive au noeud de départ. None si le trajet est court, si le profil a
+/// des zones à éviter ou des poids personnalisés, si la hiérarchie n'est pas prête ou si
+/// le chemin trouvé fait un virage interdit (la hiérarchie ignore les virages).
+pub async fn shortest_path(
+    start_node_id: i64,
+    end_node_id: i64,
+    profile: &CostProfile,
+    allow_ferry: bool,
+) -> Option<Vec<ARc<EdgePoint>>> {
+    if profile.avoid.is_some() || !profile.has_default_weights() {
+        return None;
+    }
+    let cch = current()?;
+    let start = cch.graph.node(start_node_id)?;
+    let end = cch.graph.node(end_node_id)?;
+    let ((start_lon, start_lat), (end_lon, end_lat)) =
+        (cch.coords[start as usize], cch.coords[end as usize]);
+    if distance_meters(start_lat, start_lon, end_lat, end_lon) < LONG_DISTANCE {
+        return None;
+    }
+    let metric = metric(&cch, metric_key(profile, allow_ferry), profile).await?;
+    let arcs = tokio::task::spawn_blocking({
+        let cch = cch.clone();
+        move || cch.shortest_path(&metric, start, end)
+    })
+    .await
+    .ok()??;
+
+    let overlay = cch.graph.overlay();
+    let first = cch.graph.arc(*arcs.first()?, &overlay).reverse();
+    let path: Vec<ARc<EdgePoint>> = std::iter::once(ARc::new(first))
+        .chain(arcs.iter().map(|arc| cch.graph.arc(*arc, &overlay)))
+        .collect();
+    turn_restriction::current()
+        .allows_path(&path)
+        .then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::{Edge, SourceOrTarget};
+    use crate::db::graph::GraphBuilder;
+
+    /// Remonter un edge coûte trois fois plus cher que le descendre
+    struct HDirection;
+
+    impl H for HDirection {
+        fn get_cost(&self, edge: &EdgePoint, _allow_ferry: bool) -> f64 {
+            match edge.direction {
+                SourceOrTarget::Target => 1.0,
+                SourceOrTarget::Source => 3.0,
+            }
+        }
+
+        fn get_max_point(&self) -> i64 {
+            i64::MAX
+        }
+    }
+
+    const SIZE: i64 = 12;
+
+    fn edge(id: i64, source: i64, target: i64) -> Edge {
+        let coords = |node: i64| ((node % SIZE) as f64 * 0.01, (node / SIZE) as f64 * 0.01);
+        let ((lon1, lat1), (lon2, lat2)) = (coords(source), coords(target));
+        Edge {
+            id,
+            source,
+            target,
+            lon1,
+            lat1,
+            lon2,
+            lat2,
+            way_id: id,
+            // Longueurs variées pour que le plus court chemin ne soit pas trivial
+            length: ((id * 7) % 13 + 1) as f64 * 100.0,
+            tags: sqlx::types::Json(HashMap::new()),
+            road_work_start: vec![],
+            road_work_end: vec![],
+            report_score: vec![],
+            report_date: vec![],
+            in_bicycle_route: false,
+            in_route_verte: false,
+            snow: false,
+            elevation_start: None,
+            elevation_end: None,
+            source_node_tags: None,
+            target_node_tags: None,
+        }
+    }
+
+    /// Grille de SIZE x SIZE noeuds
+    fn grid() -> ARc<Graph> {
+        let mut builder = GraphBuilder::default();
+        let mut id = 0;
+        for node in 0..SIZE * SIZE {
+            if node % SIZE != SIZE - 1 {
+                id += 1;
+                builder.push(edge(id, node, node + 1));
+            }
+            if node / SIZE != SIZE - 1 {
+                id += 1;
+                builder.push(edge(id, node, node + SIZE));
+            }
+        }
+        ARc::new(builder.build())
+    }
+
+    /// Dijkstra sur le graphe complet, pour comparaison
+    fn dijkstra(graph: &Graph, start: i64, end: i64) -> f64 {
+        let overlay = graph.overlay();
+        let mut dist: HashMap<i64, f64> = HashMap::new();
+        let mut open = BinaryHeap::new();
+        dist.insert(start, 0.0);
+        open.push((Reverse(Score(0.0)), start));
+        while let Some((Reverse(Score(cost)), node)) = open.pop() {
+            if node == end {
+                return cost;
+            }
+            if cost > dist[&node] {
+                continue;
+            }
+            for arc in graph.neighbors(node, &overlay).unwrap() {
+                let next_cost = cost + arc.length * HDirection.get_cost(&arc, true);
+                let next = arc.get_node_id();
+                if next_cost < *dist.get(&next).unwrap_or(&f64::INFINITY) {
+                    dist.insert(next, next_cost);
+                    open.push((Reverse(Score(next_cost)), next));
+                }
+            }
+        }
+        f64::INFINITY
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let graph = grid();
+        let cch = Cch::build(graph.clone());
+        let metric = cch.customize(&HDirection, true);
+        let overlay = graph.overlay();
+        for (start, end) in [(0, SIZE * SIZE - 1), (5, 100), (130, 7), (17, 17), (40, 41)] {
+            let arcs = cch
+                .shortest_path(
+                    &metric,
+                    graph.node(start).unwrap(),
+                    graph.node(end).unwrap(),
+                )
+                .unwrap();
+            let edges: Vec<ARc<EdgePoint>> =
+                arcs.iter().map(|arc| graph.arc(*arc, &overlay)).collect();
+            let cost: f64 = edges
+                .iter()
+                .map(|edge| edge.length * HDirection.get_cost(edge, true))
+                .sum();
+            assert!((cost - dijkstra(&graph, start, end)).abs() < 1e-6);
+            // Les arcs se suivent, de start à end
+            let mut node = start;
+            for edge in edges {
+                assert_eq!(edge.reverse().get_node_id(), node);
+                node = edge.get_node_id();
+            }
+            assert_eq!(node, end);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metric_shared() {
+        let cch = ARc::new(Cch::build(grid()));
+        let profile = CostProfile::new("safe", Default::default(), Default::default()).unwrap();
+        let key = metric_key(&profile, true);
+        // Deux requêtes simultanées attendent le même calcul
+        let (first, second) =
+            tokio::join!(metric(&cch, key, &profile), metric(&cch, key, &profile));
+        assert!(ARc::ptr_eq(&first.unwrap(), &second.unwrap()));
+        assert_eq!(cch.metrics.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,46 @@
+use serde::Serialize;
+use sqlx::Postgres;
+
+/// Ville ou village (limite administrative de niveau 8)
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct City {
+    pub name: String,
+    pub lng: f64,
+    pub lat: f64,
+}
+
+impl City {
+    /// Ville la plus proche d'un point, à moins de `max_distance` mètres (EPSG:3857)
+    pub async fn closest(
+        lng: f64,
+        lat: f64,
+        max_distance: f64,
+        conn: &sqlx::Pool<Postgres>,
+    ) -> Option<City> {
+        match sqlx::query_as(
+            r#"
+            SELECT
+                name,
+                ST_X(ST_Transform(ST_PointOnSurface(geom), 4326)) as lng,
+                ST_Y(ST_Transform(ST_PointOnSurface(geom), 4326)) as lat
+            FROM city
+            WHERE
+                name IS NOT NULL
+                AND ST_DWithin(geom, ST_Transform(ST_SetSRID(ST_MakePoint($1, $2), 4326), 3857), $3)
+            ORDER BY geom <-> ST_Transform(ST_SetSRID(ST_MakePoint($1, $2), 4326), 3857)
+            LIMIT 1"#,
+        )
+        .bind(lng)
+        .bind(lat)
+        .bind(max_distance)
+        .fetch_optional(conn)
+        .await
+        {
+            Ok(city) => city,
+            Err(e) => {
+                eprintln!("Error while fetching closest city: {}", e);
+                None
+            }
+        }
+    }
+}
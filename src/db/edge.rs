@@ -4,17 +4,24 @@ use std::{
     hash::Hash,
 };
 
-use crate::utils::cost::H;
-use crate::{db::utils::Score, utils::cost::get_h_moyen};
+use crate::utils::cost::{CostProfile, H};
+use crate::utils::lts;
+use crate::db::utils::{bearing, Score};
 use axum::extract::ws::WebSocket;
-use futures::future::join_all;
+use chrono::NaiveDate;
+use itertools::Either;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use sqlx::Postgres;
 use std::sync::Arc as ARc;
 use tokio::sync::Mutex;
 
+use super::ch;
 use super::cycleway::{Node, NodeDb};
+use super::graph::{self, GraphView};
+use super::report::EdgeReport;
+use super::road_work::RoadworkPeriod;
+use super::turn_restriction::{self, TurnRestrictions};
 
 lazy_static! {
     pub static ref HOST: String = std::env::var("HOST").unwrap();
@@ -31,6 +38,30 @@ pub struct Point {
     pub length: f64,
     pub ferry: bool,
     pub name: Option<String>,
+    /// Segment emprunté pour atteindre ce point (absent pour les points ajoutés à la main)
+    #[serde(skip)]
+    #[sqlx(skip)]
+    pub edge: Option<ARc<EdgePoint>>,
+}
+
+impl From<&ARc<EdgePoint>> for Point {
+    /// Point d'arrivée de l'arc
+    fn from(edge: &ARc<EdgePoint>) -> Self {
+        let (lng, lat, node_id) = match edge.direction {
+            SourceOrTarget::Source => (edge.lon1, edge.lat1, edge.source),
+            SourceOrTarget::Target => (edge.lon2, edge.lat2, edge.target),
+        };
+        Point {
+            lng,
+            lat,
+            way_id: edge.way_id,
+            node_id,
+            length: edge.length,
+            ferry: matches!(edge.route, Some(Route::Ferry)),
+            name: edge.name.clone(),
+            edge: Some(edge.clone()),
+        }
+    }
 }
 
 impl std::fmt::Display for Point {
@@ -56,15 +87,23 @@ pub struct Edge {
     pub lat1: f64,
     pub lon2: f64,
     pub lat2: f64,
-    pub reported: bool,
     pub way_id: i64,
     pub length: f64,
     pub tags: sqlx::types::Json<HashMap<String, String>>,
-    pub road_work: bool,
+    /// Dates de début et de fin des entraves qui touchent l'edge
+    pub road_work_start: Vec<Option<NaiveDate>>,
+    pub road_work_end: Vec<Option<NaiveDate>>,
+    /// Cote et jour des signalements actifs qui touchent l'edge
+    pub report_score: Vec<f64>,
+    pub report_date: Vec<NaiveDate>,
     pub in_bicycle_route: bool,
+    pub in_route_verte: bool,
     pub snow: bool,
     pub elevation_start: Option<i16>,
     pub elevation_end: Option<i16>,
+    /// Tags des noeuds de départ et d'arrivée qui portent une barrière, une traversée ou des feux
+    pub source_node_tags: Option<sqlx::types::Json<HashMap<String, String>>>,
+    pub target_node_tags: Option<sqlx::types::Json<HashMap<String, String>>>,
 }
 
 impl Eq for Edge {}
@@ -92,12 +131,15 @@ pub struct EdgePoint {
     pub source: i64,
     pub target: i64,
     pub in_bicycle_route: bool,
+    pub in_route_verte: bool,
     pub route: Option<Route>,
-    pub road_work: bool,
+    /// Entraves qui touchent l'edge, quelle que soit leur date
+    pub road_work: Vec<RoadworkPeriod>,
     pub snow: bool,
     pub winter_service_no: bool,
     pub abandoned: bool,
-    pub reported: bool,
+    /// Signalements actifs, quelle que soit leur date
+    pub reports: Vec<EdgeReport>,
     pub direction: SourceOrTarget,
     pub cycleway: Option<Cycleway>,
     pub cycleway_left: Option<Cycleway>,
@@ -122,6 +164,59 @@ pub struct EdgePoint {
     pub elevation_start: Option<i16>,
     pub elevation_end: Option<i16>,
     pub name: Option<String>,
+    pub roundabout: bool,
+    pub barrier: Option<Barrier>,
+    /// Largeur de la voie en mètres (`width`)
+    pub width: Option<f64>,
+    /// Largeur maximale permise en mètres (`maxwidth`)
+    pub maxwidth: Option<f64>,
+    /// Aménagements fermés en hiver (`cycleway:*:conditional=no @ snow`)
+    pub snow_closed: SnowClosed,
+    /// Type de traversée (`crossing`, `crossing:signals`)
+    pub crossing: Option<Crossing>,
+    /// Artère la plus importante à chaque bout de l'edge. Rempli par le graphe en mémoire.
+    pub junction_source: Option<Highway>,
+    pub junction_target: Option<Highway>,
+    /// Barrière, accès et traversée des noeuds de départ et d'arrivée
+    pub source_node: Option<NodeAttributes>,
+    pub target_node: Option<NodeAttributes>,
+    /// Niveau de stress de la circulation, de 1 à 4 (voir `utils::lts`)
+    pub lts: u8,
+}
+
+/// Attributs d'un noeud OSM qui comptent pour le passage d'un vélo
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeAttributes {
+    pub barrier: Option<Barrier>,
+    pub bicycle: Option<Bicycle>,
+    pub access: Option<Access>,
+    /// `locked=yes`: barrière fermée à clé
+    pub locked: bool,
+    /// Largeur de passage en mètres (`maxwidth:physical` ou `maxwidth`)
+    pub maxwidth: Option<f64>,
+    pub crossing: Option<Crossing>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SnowClosed {
+    pub cycleway: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+/// État dynamique d'un edge (signalements, entraves, déneigement), appliqué
+/// par-dessus les attributs tirés des tags OSM.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EdgeOverlay {
+    pub reports: Vec<EdgeReport>,
+    pub snow: bool,
+    pub road_work: Vec<RoadworkPeriod>,
+}
+
+impl EdgeOverlay {
+    pub fn is_empty(&self) -> bool {
+        self.reports.is_empty() && !self.snow && self.road_work.is_empty()
+    }
 }
 
 impl Default for EdgePoint {
@@ -137,11 +232,12 @@ impl Default for EdgePoint {
             source: 0,
             target: 0,
             in_bicycle_route: false,
-            road_work: false,
+            in_route_verte: false,
+            road_work: vec![],
             snow: false,
             winter_service_no: false,
             abandoned: false,
-            reported: false,
+            reports: vec![],
             direction: SourceOrTarget::Target,
             cycleway: None,
             cycleway_left: None,
@@ -167,6 +263,17 @@ impl Default for EdgePoint {
             elevation_start: None,
             elevation_end: None,
             name: None,
+            roundabout: false,
+            barrier: None,
+            width: None,
+            maxwidth: None,
+            snow_closed: SnowClosed::default(),
+            crossing: None,
+            junction_source: None,
+            junction_target: None,
+            source_node: None,
+            target_node: None,
+            lts: 1,
         }
     }
 }
@@ -233,17 +340,62 @@ pub enum Bicycle {
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Surface {
+    Asphalt,
+    Concrete,
+    PavingStones,
+    Paved,
+    Wood,
+    Metal,
     Sett,
     Cobblestone,
     UnhewnCobblestone,
     Gravel,
     FineGravel,
     Chipseal,
+    Compacted,
+    Pebblestone,
+    Unpaved,
+    Dirt,
+    Ground,
+    Earth,
+    Mud,
+    Grass,
+    Sand,
+    Woodchips,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+impl Surface {
+    /// Revêtement non asphalté ni pavé
+    pub fn is_unpaved(&self) -> bool {
+        matches!(
+            self,
+            Surface::Gravel
+                | Surface::FineGravel
+                | Surface::Compacted
+                | Surface::Pebblestone
+                | Surface::Unpaved
+                | Surface::Dirt
+                | Surface::Ground
+                | Surface::Earth
+                | Surface::Mud
+                | Surface::Grass
+                | Surface::Sand
+                | Surface::Woodchips
+        )
+    }
+}
+
+/// Valeurs de `smoothness`, de la meilleure à la pire
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Smoothness {
+    Excellent,
+    Good,
+    Intermediate,
     Bad,
+    VeryBad,
+    Horrible,
+    VeryHorrible,
+    Impassable,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -264,6 +416,25 @@ pub enum Tunnel {
     Yes,
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Barrier {
+    Cycle,
+    Bollard,
+    Gate,
+    LiftGate,
+    /// Tourniquet, échalier: il faut soulever le vélo
+    Stile,
+    RaisedKerb,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Crossing {
+    TrafficSignals,
+    Marked,
+    Unmarked,
+}
+
 #[derive(Debug, Clone, Eq, Hash)]
 pub enum SourceOrTarget {
     Source,
@@ -370,12 +541,28 @@ impl From<(ARc<Edge>, SourceOrTarget)> for EdgePoint {
         let parse_surface = |v: Option<&String>| -> Option<Surface> {
             match v {
                 Some(s) => match s.as_str() {
+                    "asphalt" => Some(Surface::Asphalt),
+                    "concrete" | "concrete:plates" | "concrete:lanes" => Some(Surface::Concrete),
+                    "paving_stones" => Some(Surface::PavingStones),
+                    "paved" => Some(Surface::Paved),
+                    "wood" => Some(Surface::Wood),
+                    "metal" => Some(Surface::Metal),
                     "sett" => Some(Surface::Sett),
                     "cobblestone" => Some(Surface::Cobblestone),
                     "unhewn_cobblestone" => Some(Surface::UnhewnCobblestone),
                     "gravel" => Some(Surface::Gravel),
                     "fine_gravel" => Some(Surface::FineGravel),
                     "chipseal" => Some(Surface::Chipseal),
+                    "compacted" => Some(Surface::Compacted),
+                    "pebblestone" => Some(Surface::Pebblestone),
+                    "unpaved" => Some(Surface::Unpaved),
+                    "dirt" => Some(Surface::Dirt),
+                    "ground" => Some(Surface::Ground),
+                    "earth" => Some(Surface::Earth),
+                    "mud" => Some(Surface::Mud),
+                    "grass" | "grass_paver" => Some(Surface::Grass),
+                    "sand" => Some(Surface::Sand),
+                    "woodchips" => Some(Surface::Woodchips),
                     _ => None,
                 },
                 None => None,
@@ -385,7 +572,14 @@ impl From<(ARc<Edge>, SourceOrTarget)> for EdgePoint {
         let parse_smoothness = |v: Option<&String>| -> Option<Smoothness> {
             match v {
                 Some(s) => match s.as_str() {
+                    "excellent" => Some(Smoothness::Excellent),
+                    "good" => Some(Smoothness::Good),
+                    "intermediate" => Some(Smoothness::Intermediate),
                     "bad" => Some(Smoothness::Bad),
+                    "very_bad" => Some(Smoothness::VeryBad),
+                    "horrible" => Some(Smoothness::Horrible),
+                    "very_horrible" => Some(Smoothness::VeryHorrible),
+                    "impassable" => Some(Smoothness::Impassable),
                     _ => None,
                 },
                 None => None,
@@ -435,6 +629,73 @@ impl From<(ARc<Edge>, SourceOrTarget)> for EdgePoint {
             }
         };
 
+        let parse_barrier = |v: Option<&String>| -> Option<Barrier> {
+            match v {
+                Some(s) => match s.as_str() {
+                    "cycle_barrier" => Some(Barrier::Cycle),
+                    _ => None,
+                },
+                None => None,
+            }
+        };
+
+        let parse_crossing = |v: Option<&String>, signals: Option<&String>| -> Option<Crossing> {
+            if signals.map(|s| s.as_str()) == Some("yes") {
+                return Some(Crossing::TrafficSignals);
+            }
+            match v?.as_str() {
+                "traffic_signals" => Some(Crossing::TrafficSignals),
+                "marked" | "zebra" | "uncontrolled" => Some(Crossing::Marked),
+                "unmarked" | "no" => Some(Crossing::Unmarked),
+                _ => None,
+            }
+        };
+
+        // Largeurs en mètres: "1.5", "1.5 m" ou "150 cm"
+        let parse_width = |v: Option<&String>| -> Option<f64> {
+            let s = v?.trim();
+            if let Some(cm) = s.strip_suffix("cm") {
+                return cm.trim().parse::<f64>().ok().map(|cm| cm / 100.0);
+            }
+            s.trim_end_matches('m').trim().parse::<f64>().ok()
+        };
+
+        let parse_node_barrier = |tags: &HashMap<String, String>| -> Option<Barrier> {
+            let kerb_raised = tags.get("kerb").map(|s| s.as_str()) == Some("raised");
+            match tags.get("barrier").map(|s| s.as_str()) {
+                None | Some("kerb") if kerb_raised => Some(Barrier::RaisedKerb),
+                None | Some("kerb") | Some("no") | Some("entrance") | Some("border_control") => {
+                    None
+                }
+                Some("cycle_barrier") => Some(Barrier::Cycle),
+                Some("bollard") => Some(Barrier::Bollard),
+                Some("gate") | Some("swing_gate") | Some("sliding_gate") => Some(Barrier::Gate),
+                Some("lift_gate") => Some(Barrier::LiftGate),
+                Some("stile") | Some("turnstile") | Some("kissing_gate") | Some("full-height_turnstile") => {
+                    Some(Barrier::Stile)
+                }
+                Some(_) => Some(Barrier::Other),
+            }
+        };
+
+        let parse_node = |tags: Option<&sqlx::types::Json<HashMap<String, String>>>| {
+            let tags = &tags?.0;
+            let get = |k: &str| -> Option<&String> { tags.get(k) };
+            let crossing = if get("highway").map(|s| s.as_str()) == Some("traffic_signals") {
+                Some(Crossing::TrafficSignals)
+            } else {
+                parse_crossing(get("crossing"), get("crossing:signals"))
+            };
+            Some(NodeAttributes {
+                barrier: parse_node_barrier(tags),
+                bicycle: parse_bicycle(get("bicycle")),
+                access: parse_access(get("access")),
+                locked: get("locked").map(|s| s.as_str()) == Some("yes"),
+                maxwidth: parse_width(get("maxwidth:physical")).or(parse_width(get("maxwidth"))),
+                crossing,
+            })
+        };
+
         let parse_lcn = |v: Option<&String>| -> bool {
             match v {
                 Some(s) => match s.as_str() {
@@ -487,25 +748,14 @@ impl From<(ARc<Edge>, SourceOrTarget)> for EdgePoint {
             source: edge.source,
             target: edge.target,
             in_bicycle_route: edge.in_bicycle_route,
-            road_work: edge.road_work,
-            snow: edge.snow,
-            reported: edge.reported,
+            in_route_verte: edge.in_route_verte,
+            road_work: vec![],
+            snow: false,
+            reports: vec![],
             direction,
-            cycleway: if edge.snow && cycleway_conditional_no_snow {
-                Some(Cycleway::Snow)
-            } else {
-                parse_cycleway(get("cycleway"))
-            },
-            cycleway_left: if edge.snow && cycleway_left_conditional_no_snow {
-                Some(Cycleway::Snow)
-            } else {
-                parse_cycleway(get("cycleway:left"))
-            },
-            cycleway_right: if edge.snow && cycleway_right_conditional_no_snow {
-                Some(Cycleway::Snow)
-            } else {
-                parse_cycleway(get("cycleway:right"))
-            },
+            cycleway: parse_cycleway(get("cycleway")),
+            cycleway_left: parse_cycleway(get("cycleway:left")),
+            cycleway_right: parse_cycleway(get("cycleway:right")),
             cycleway_both: parse_cycleway(get("cycleway:both")),
             highway: parse_highway(get("highway")),
             route: parse_route(get("route")),
@@ -530,13 +780,72 @@ impl From<(ARc<Edge>, SourceOrTarget)> for EdgePoint {
             elevation_start: edge.elevation_start,
             elevation_end: edge.elevation_end,
             name: extract_name(&edge.tags.0),
+            roundabout: matches!(
+                get("junction").map(|s| s.as_str()),
+                Some("roundabout") | Some("circular")
+            ),
+            barrier: parse_barrier(get("barrier")),
+            width: parse_width(get("width")),
+            maxwidth: parse_width(get("maxwidth")),
+            snow_closed: SnowClosed {
+                cycleway: cycleway_conditional_no_snow,
+                left: cycleway_left_conditional_no_snow,
+                right: cycleway_right_conditional_no_snow,
+            },
+            crossing: parse_crossing(get("crossing"), get("crossing:signals")),
+            junction_source: None,
+            junction_target: None,
+            source_node: parse_node(edge.source_node_tags.as_ref()),
+            target_node: parse_node(edge.target_node_tags.as_ref()),
+            lts: lts::classify(tags),
         };
 
-        ep
+        ep.with_overlay(&EdgeOverlay {
+            reports: edge
+                .report_score
+                .iter()
+                .zip(edge.report_date.iter())
+                .map(|(score, date)| EdgeReport {
+                    score: *score,
+                    date: *date,
+                })
+                .collect(),
+            snow: edge.snow,
+            road_work: edge
+                .road_work_start
+                .iter()
+                .zip(edge.road_work_end.iter())
+                .map(|(start_date, end_date)| RoadworkPeriod {
+                    start_date: *start_date,
+                    end_date: *end_date,
+                })
+                .collect(),
+        })
     }
 }
 
 impl EdgePoint {
+    /// Copie de l'edge avec l'état dynamique `overlay`. `self` doit être
+    /// l'edge tel que lu des tags, sans overlay.
+    pub fn with_overlay(&self, overlay: &EdgeOverlay) -> EdgePoint {
+        let mut edge = self.clone();
+        edge.reports = overlay.reports.clone();
+        edge.snow = overlay.snow;
+        edge.road_work = overlay.road_work.clone();
+        if overlay.snow {
+            if self.snow_closed.cycleway {
+                edge.cycleway = Some(Cycleway::Snow);
+            }
+            if self.snow_closed.left {
+                edge.cycleway_left = Some(Cycleway::Snow);
+            }
+            if self.snow_closed.right {
+                edge.cycleway_right = Some(Cycleway::Snow);
+            }
+        }
+        edge
+    }
+
     pub fn reverse(&self) -> Self {
         let new_direction = match self.direction {
             SourceOrTarget::Source => SourceOrTarget::Target,
@@ -547,6 +856,52 @@ impl EdgePoint {
         e
     }
 
+    /// Vrai si une entrave touchant l'edge est en vigueur le jour `date`
+    pub fn road_work_on(&self, date: NaiveDate) -> bool {
+        self.road_work.iter().any(|period| period.is_active(date))
+    }
+
+    /// Multiplicateur de coût des signalements le jour `date`. Le plus récent
+    /// décrit l'état actuel du segment et remplace les précédents.
+    pub fn report_penalty(&self, date: NaiveDate) -> f64 {
+        self.reports
+            .iter()
+            .max_by_key(|report| report.date)
+            .map_or(1.0, |report| report.penalty(date))
+    }
+
+    /// Largeur utilisable la plus restrictive connue, en mètres
+    pub fn min_width(&self) -> Option<f64> {
+        match (self.width, self.maxwidth) {
+            (Some(width), Some(maxwidth)) => Some(width.min(maxwidth)),
+            (width, maxwidth) => width.or(maxwidth),
+        }
+    }
+
+    /// Attributs du noeud d'arrivée, s'il porte une barrière, une traversée ou des feux
+    pub fn arrival_node(&self) -> Option<&NodeAttributes> {
+        match self.direction {
+            SourceOrTarget::Source => self.source_node.as_ref(),
+            SourceOrTarget::Target => self.target_node.as_ref(),
+        }
+    }
+
+    /// Artère la plus importante au noeud d'arrivée
+    pub fn junction(&self) -> Option<&Highway> {
+        match self.direction {
+            SourceOrTarget::Source => self.junction_source.as_ref(),
+            SourceOrTarget::Target => self.junction_target.as_ref(),
+        }
+    }
+
+    /// Cap en degrés dans le sens du parcours
+    pub fn bearing(&self) -> f64 {
+        match self.direction {
+            SourceOrTarget::Source => bearing(self.lat2, self.lon2, self.lat1, self.lon1),
+            SourceOrTarget::Target => bearing(self.lat1, self.lon1, self.lat2, self.lon2),
+        }
+    }
+
     pub fn get_node_id(&self) -> i64 {
         match self.direction {
             SourceOrTarget::Source => self.source,
@@ -554,7 +909,21 @@ impl EdgePoint {
         }
     }
 
-    pub async fn get_neighbors(&self, conn: &sqlx::Pool<Postgres>) -> ARc<Vec<ARc<EdgePoint>>> {
+    /// Arcs qui quittent le noeud d'arrivée: ceux de `graph` si le noeud y est, sinon lus de la base
+    pub async fn get_neighbors<'a>(
+        &self,
+        graph: Option<&'a GraphView>,
+        conn: &sqlx::Pool<Postgres>,
+    ) -> Neighbors<'a> {
+        let node_id = self.get_node_id();
+        if let Some((graph, arcs)) = graph.and_then(|graph| Some((graph, graph.out_arcs(node_id)?)))
+        {
+            return Neighbors::Graph(graph, arcs);
+        }
+        Neighbors::Db(self.get_db_neighbors(conn).await)
+    }
+
+    async fn get_db_neighbors(&self, conn: &sqlx::Pool<Postgres>) -> ARc<Vec<ARc<EdgePoint>>> {
         let node_id = self.get_node_id();
         if let Some(neighbors) = NEIGHBORS_CACHE.lock().await.get(node_id).await {
             return neighbors;
@@ -571,16 +940,30 @@ impl EdgePoint {
             e.tags,
             e.way_id,
             e.in_bicycle_route,
+            e.in_route_verte,
             e.tags->>'name' as name,
             st_length(ST_Transform(e.geom, 4326)::geography) as length,
-            rw.geom is not null as road_work,
-            r.geom is not null as reported,
+            coalesce(rw.start_dates, '{}') as road_work_start,
+            coalesce(rw.end_dates, '{}') as road_work_end,
+            coalesce(r.scores, '{}') as report_score,
+            coalesce(r.dates, '{}') as report_date,
             case when csnow.city_name is not null then true else false end as snow,
             e.elevation_start,
-            e.elevation_end
+            e.elevation_end,
+            e.source_node_tags,
+            e.target_node_tags
         FROM edge e
-            left join road_work rw on ST_Intersects(e.geom, rw.geom)
-            left join report r on ST_Intersects(e.geom, r.geom) and r.enabled = true
+            left join lateral (
+                select array_agg(w.start_date) as start_dates, array_agg(w.end_date) as end_dates
+                from road_work w
+                where ST_Intersects(e.geom, w.geom)
+            ) rw on true
+            left join lateral (
+                select array_agg(r.score) as scores,
+                    array_agg((r.created_at at time zone 'America/Montreal')::date) as dates
+                from report r
+                where ST_Intersects(e.geom, r.geom) and r.enabled = true
+            ) r on true
             left join city_snow csnow on csnow.city_name = e.city_name
         WHERE (e.source = $1 or e.target = $1)
         "#;
@@ -615,6 +998,22 @@ impl EdgePoint {
     }
 }
 
+/// Arcs qui quittent un noeud: des index dans le graphe en mémoire, résolus au fil de
+/// l'itération, ou les edges lus de la base
+pub enum Neighbors<'a> {
+    Graph(&'a GraphView, &'a [u32]),
+    Db(ARc<Vec<ARc<EdgePoint>>>),
+}
+
+impl Neighbors<'_> {
+    pub fn iter(&self) -> impl Iterator<Item = ARc<EdgePoint>> + '_ {
+        match self {
+            Neighbors::Graph(graph, arcs) => Either::Left(arcs.iter().map(|arc| graph.arc(*arc))),
+            Neighbors::Db(neighbors) => Either::Right(neighbors.iter().cloned()),
+        }
+    }
+}
+
 use std::collections::VecDeque;
 
 struct EdgePointCache {
@@ -668,17 +1067,101 @@ lazy_static! {
     static ref NEIGHBORS_CACHE: Mutex<EdgePointCache> = Mutex::new(EdgePointCache::new());
 }
 
+/// (prédécesseurs, scores) d'une des deux recherches de `a_star_bidirectional`
+type SearchState<'a> = (
+    &'a HashMap<ARc<EdgePoint>, ARc<EdgePoint>>,
+    &'a HashMap<ARc<EdgePoint>, f64>,
+);
+
+/// Coût du chemin qui passe par `meeting`, atteint par les deux recherches de
+/// `a_star_bidirectional`. Elles parcourent `meeting` en sens inverse l'une de l'autre:
+/// une fois le demi-tour retiré, le chemin arrive au noeud par le prédécesseur avant et
+/// en repart par le prédécesseur arrière. None si ce virage est interdit.
+fn meeting_score(
+    meeting: &ARc<EdgePoint>,
+    (came_from_fwd, g_score_fwd): SearchState,
+    (came_from_bwd, g_score_bwd): SearchState,
+    (start_node, end_node): (&ARc<EdgePoint>, &ARc<EdgePoint>),
+    restrictions: &TurnRestrictions,
+    h: &dyn H,
+) -> Option<f64> {
+    let (score_fwd, score_bwd) = (g_score_fwd.get(meeting)?, g_score_bwd.get(meeting)?);
+    let (Some(from), Some(next)) = (came_from_fwd.get(meeting), came_from_bwd.get(meeting)) else {
+        return Some(score_fwd + score_bwd);
+    };
+    // Départ ou arrivée: il n'y a pas de virage
+    if from == start_node {
+        return Some(g_score_bwd[next]);
+    }
+    if next == end_node {
+        return Some(g_score_fwd[from]);
+    }
+    let to = next.reverse();
+    if !restrictions.allows(from.get_node_id(), from, &to) {
+        return None;
+    }
+    Some(g_score_fwd[from] + h.turn_cost(from, &to) + g_score_bwd[next])
+}
+
 impl Edge {
+    /// Itinéraire selon `profile`: par la hiérarchie de contraction pour les longs trajets,
+    /// sinon (ou tant qu'elle n'est pas prête) par le A* bidirectionnel.
+    pub async fn route(
+        start_node_id: i64,
+        end_node_id: i64,
+        profile: &CostProfile,
+        conn: &sqlx::Pool<Postgres>,
+        socket: Option<&mut WebSocket>,
+        allow_ferry: bool,
+    ) -> Vec<Point> {
+        if let Some(path) =
+            ch::shortest_path(start_node_id, end_node_id, profile, allow_ferry).await
+        {
+            return path.iter().map(Point::from).collect();
+        }
+        Edge::a_star_bidirectional(
+            start_node_id,
+            end_node_id,
+            profile.h(),
+            conn,
+            socket,
+            allow_ferry,
+        )
+        .await
+    }
+
     pub async fn a_star_bidirectional(
         start_node_id: i64,
         end_node_id: i64,
         h: Box<dyn H>,
         conn: &sqlx::Pool<Postgres>,
+        socket: Option<&mut WebSocket>,
+        allow_ferry: bool,
+    ) -> Vec<Point> {
+        Edge::a_star_bidirectional_in(
+            GraphView::current().as_ref(),
+            &turn_restriction::current(),
+            (start_node_id, end_node_id),
+            h,
+            conn,
+            socket,
+            allow_ferry,
+        )
+        .await
+    }
+
+    /// A* bidirectionnel dans `graph` (dans la base si None) avec les restrictions `restrictions`
+    async fn a_star_bidirectional_in(
+        graph: Option<&GraphView>,
+        restrictions: &TurnRestrictions,
+        (start_node_id, end_node_id): (i64, i64),
+        h: Box<dyn H>,
+        conn: &sqlx::Pool<Postgres>,
         mut socket: Option<&mut WebSocket>,
         allow_ferry: bool,
     ) -> Vec<Point> {
-        let start_node = Edge::get(start_node_id, conn).await.unwrap();
-        let end_node = Edge::get(end_node_id, conn).await.unwrap();
+        let start_node = Edge::get_in(start_node_id, graph, conn).await.unwrap();
+        let end_node = Edge::get_in(end_node_id, graph, conn).await.unwrap();
 
         // --- Structures pour la recherche AVANT (start -> end) ---
         let mut open_set_fwd = BTreeMap::new();
@@ -736,18 +1219,37 @@ impl Edge {
                     }
                 }
 
-                if g_score_bwd.contains_key(&current_fwd) {
-                    let current_score = g_score_fwd[&current_fwd] + g_score_bwd[&current_fwd];
+                if let Some(current_score) = meeting_score(
+                    &current_fwd,
+                    (&came_from_fwd, &g_score_fwd),
+                    (&came_from_bwd, &g_score_bwd),
+                    (&start_node, &end_node),
+                    restrictions,
+                    h.as_ref(),
+                ) {
                     if current_score < best_path_score {
                         best_path_score = current_score;
                         meeting_point = Some(current_fwd.clone());
                     }
                 }
 
-                for neighbor in current_fwd.get_neighbors(conn).await.iter() {
+                for neighbor in current_fwd.get_neighbors(graph, conn).await.iter() {
+                    // Le départ n'est pas un virage
+                    let turn_cost = if current_fwd == start_node {
+                        0.0
+                    } else if restrictions.allows(
+                        current_fwd.get_node_id(),
+                        &current_fwd,
+                        &neighbor,
+                    ) {
+                        h.turn_cost(&current_fwd, &neighbor)
+                    } else {
+                        continue;
+                    };
                     let tentative_g_score = g_score_fwd[&current_fwd]
-                        + neighbor.length * h.get_cost(&neighbor, allow_ferry);
-                    if tentative_g_score < *g_score_fwd.get(neighbor).unwrap_or(&f64::INFINITY) {
+                        + neighbor.length * h.get_cost(&neighbor, allow_ferry)
+                        + turn_cost;
+                    if tentative_g_score < *g_score_fwd.get(&neighbor).unwrap_or(&f64::INFINITY) {
                         came_from_fwd.insert(neighbor.clone(), current_fwd.clone());
                         g_score_fwd.insert(neighbor.clone(), tentative_g_score);
                         let f_score = tentative_g_score + h.h(&neighbor, &end_node);
@@ -775,18 +1277,40 @@ impl Edge {
                     }
                 }
 
-                if g_score_fwd.contains_key(&current_bwd) {
-                    let current_score = g_score_fwd[&current_bwd] + g_score_bwd[&current_bwd];
+                if let Some(current_score) = meeting_score(
+                    &current_bwd,
+                    (&came_from_fwd, &g_score_fwd),
+                    (&came_from_bwd, &g_score_bwd),
+                    (&start_node, &end_node),
+                    restrictions,
+                    h.as_ref(),
+                ) {
                     if current_score < best_path_score {
                         best_path_score = current_score;
                         meeting_point = Some(current_bwd.clone());
                     }
                 }
 
-                for neighbor in current_bwd.get_neighbors(conn).await.iter() {
+                // Dans le sens du parcours, on repart du noeud par `current_bwd` inversé
+                let next_bwd = current_bwd.reverse();
+                for neighbor in current_bwd.get_neighbors(graph, conn).await.iter() {
+                    // En arrière, on arrive au noeud par `neighbor` inversé
+                    let from = neighbor.reverse();
+                    let turn_cost = if current_bwd == end_node {
+                        0.0
+                    } else if restrictions.allows(
+                        current_bwd.get_node_id(),
+                        &neighbor,
+                        &current_bwd,
+                    ) {
+                        h.turn_cost(&from, &next_bwd)
+                    } else {
+                        continue;
+                    };
                     let tentative_g_score = g_score_bwd[&current_bwd]
-                        + neighbor.length * h.get_cost(&neighbor.reverse(), allow_ferry);
-                    if tentative_g_score < *g_score_bwd.get(neighbor).unwrap_or(&f64::INFINITY) {
+                        + neighbor.length * h.get_cost(&from, allow_ferry)
+                        + turn_cost;
+                    if tentative_g_score < *g_score_bwd.get(&neighbor).unwrap_or(&f64::INFINITY) {
                         came_from_bwd.insert(neighbor.clone(), current_bwd.clone());
                         g_score_bwd.insert(neighbor.clone(), tentative_g_score);
                         let f_score = tentative_g_score + h.h(&neighbor, &start_node);
@@ -886,38 +1410,7 @@ impl Edge {
                 path
             };
 
-            let promises = path
-                .iter()
-                .map(|edge| async {
-                    match edge.direction {
-                        SourceOrTarget::Source => Point {
-                            lng: edge.lon1,
-                            lat: edge.lat1,
-                            way_id: edge.way_id,
-                            node_id: edge.source,
-                            length: edge.length,
-                            ferry: match edge.route {
-                                Some(Route::Ferry) => true,
-                                _ => false,
-                            },
-                            name: edge.name.clone(),
-                        },
-                        SourceOrTarget::Target => Point {
-                            lng: edge.lon2,
-                            lat: edge.lat2,
-                            way_id: edge.way_id,
-                            node_id: edge.target,
-                            length: edge.length,
-                            ferry: match edge.route {
-                                Some(Route::Ferry) => true,
-                                _ => false,
-                            },
-                            name: edge.name.clone(),
-                        },
-                    }
-                })
-                .collect::<Vec<_>>();
-            return join_all(promises).await;
+            return path.iter().map(Point::from).collect();
         }
 
         vec![]
@@ -1001,6 +1494,19 @@ impl Edge {
         node_id: i64,
         conn: &sqlx::Pool<Postgres>,
     ) -> Result<ARc<EdgePoint>, sqlx::Error> {
+        Edge::get_in(node_id, GraphView::current().as_ref(), conn).await
+    }
+
+    /// Comme `get`, dans `graph` s'il y en a un
+    async fn get_in(
+        node_id: i64,
+        graph: Option<&GraphView>,
+        conn: &sqlx::Pool<Postgres>,
+    ) -> Result<ARc<EdgePoint>, sqlx::Error> {
+        // Un arc qui quitte le noeud, retourné pour que le noeud courant soit `node_id`
+        if let Some(arc) = graph.and_then(|graph| graph.neighbors(node_id)?.next()) {
+            return Ok(ARc::new(arc.reverse()));
+        }
         let edge: Result<Edge, _> = sqlx::query_as(
             r#"SELECT
                 e.id,
@@ -1014,15 +1520,29 @@ impl Edge {
                 e.way_id,
                 e.tags->>'name' as name, 
                 st_length(e.geom) as length,
-                rw.geom is not null as road_work,
+                coalesce(rw.start_dates, '{}') as road_work_start,
+            coalesce(rw.end_dates, '{}') as road_work_end,
                 in_bicycle_route,
-                r.geom is not null as reported,
+                e.in_route_verte,
+                coalesce(r.scores, '{}') as report_score,
+                coalesce(r.dates, '{}') as report_date,
                 case when csnow.city_name is not null then true else false end as snow,
                 e.elevation_start,
-                e.elevation_end
+                e.elevation_end,
+                e.source_node_tags,
+                e.target_node_tags
             FROM edge e
-                left join road_work rw on ST_Intersects(e.geom, rw.geom)
-                left join report r on ST_Intersects(e.geom, r.geom) and r.enabled = true
+                left join lateral (
+                select array_agg(w.start_date) as start_dates, array_agg(w.end_date) as end_dates
+                from road_work w
+                where ST_Intersects(e.geom, w.geom)
+            ) rw on true
+                left join lateral (
+                    select array_agg(r.score) as scores,
+                        array_agg((r.created_at at time zone 'America/Montreal')::date) as dates
+                    from report r
+                    where ST_Intersects(e.geom, r.geom) and r.enabled = true
+                ) r on true
                 left join city_snow csnow on csnow.city_name = e.city_name
             WHERE e.source = $1 or e.target = $1"#,
         )
@@ -1060,15 +1580,29 @@ impl Edge {
                 e.way_id,
                 e.tags->>'name' as name, 
                 st_length(e.geom) as length,
-                rw.geom is not null as road_work,
+                coalesce(rw.start_dates, '{}') as road_work_start,
+            coalesce(rw.end_dates, '{}') as road_work_end,
                 in_bicycle_route,
-                r.geom is not null as reported,
+                e.in_route_verte,
+                coalesce(r.scores, '{}') as report_score,
+                coalesce(r.dates, '{}') as report_date,
                 case when csnow.city_name is not null then true else false end as snow,
                 e.elevation_start,
-                e.elevation_end
+                e.elevation_end,
+                e.source_node_tags,
+                e.target_node_tags
             FROM edge e
-            left join road_work rw on ST_Intersects(e.geom, rw.geom)
-            left join report r on ST_Intersects(e.geom, r.geom) and r.enabled = true
+            left join lateral (
+                select array_agg(w.start_date) as start_dates, array_agg(w.end_date) as end_dates
+                from road_work w
+                where ST_Intersects(e.geom, w.geom)
+            ) rw on true
+            left join lateral (
+                select array_agg(r.score) as scores,
+                    array_agg((r.created_at at time zone 'America/Montreal')::date) as dates
+                from report r
+                where ST_Intersects(e.geom, r.geom) and r.enabled = true
+            ) r on true
             left join city_snow csnow on csnow.city_name = e.city_name
             WHERE e.city_name = $1"#,
         )
@@ -1119,14 +1653,15 @@ impl Edge {
             .unwrap();
 
             // 3. Clear cache des nodes affectés
-            Edge::clear_nodes_cache(node_ids).await;
+            Edge::clear_nodes_cache(node_ids, &conn).await;
         });
     }
 
-    pub async fn clear_nodes_cache(node_ids: Vec<i64>) {
-        for node_id in node_ids {
-            NEIGHBORS_CACHE.lock().await.remove(node_id).await;
+    pub async fn clear_nodes_cache(node_ids: Vec<i64>, conn: &sqlx::Pool<Postgres>) {
+        for node_id in &node_ids {
+            NEIGHBORS_CACHE.lock().await.remove(*node_id).await;
         }
+        graph::refresh_overlay(&node_ids, conn).await;
     }
 
     pub async fn clear_all_cache() {
@@ -1137,30 +1672,17 @@ impl Edge {
         Edge::clear_all_cache().await;
         let conn = conn.clone();
         tokio::spawn(async move {
-            println!("Starting cache prefill...");
-
-            let routes = vec![
-                (11329849232, 177522966, "Montréal to Quebec"),
-                (2352518821, 1870784004, "Mont-Tremblant to Sherbrooke"),
-                (11329849232, 60890409, "Montréal to Gatineau"),
-            ];
-
-            for (source, target, description) in routes {
-                println!("Calculating route: {}", description);
-                Edge::a_star_bidirectional(source, target, get_h_moyen(), &conn, None, true).await;
-            }
-
-            println!("Cache prefill complete");
+            println!("Loading routing graph...");
+            graph::reload(&conn).await;
         });
     }
 }
 
 #[cfg(test)]
 mod tests {
-
-    use crate::utils::cost::get_h_moyen;
-
     use super::*;
+    use crate::db::graph::GraphBuilder;
+    use crate::utils::cost::{BikeType, Preferences};
     use std::env;
 
     #[tokio::test]
@@ -1168,10 +1690,91 @@ mod tests {
         let conn = sqlx::Pool::connect(&env::var("DATABASE_URL").unwrap())
             .await
             .unwrap();
-        let points =
-            Edge::a_star_bidirectional(321801851, 1764306722, get_h_moyen(), &conn, None, true)
-                .await;
+        let profile = CostProfile::new("safe", BikeType::Regular, Preferences::default()).unwrap();
+        let points = Edge::route(321801851, 1764306722, &profile, &conn, None, true).await;
         assert_eq!(321801851, points.first().unwrap().node_id);
         assert_eq!(1764306722, points.last().unwrap().node_id);
     }
+
+    /// Coût égal à la longueur, sans virages ni heuristique
+    struct HLength;
+
+    impl H for HLength {
+        fn get_cost(&self, _edge: &EdgePoint, _allow_ferry: bool) -> f64 {
+            1.0
+        }
+
+        fn get_max_point(&self) -> i64 {
+            i64::MAX
+        }
+
+        fn turn_cost(&self, _from: &EdgePoint, _to: &EdgePoint) -> f64 {
+            0.0
+        }
+
+        fn h(&self, _start_point: &EdgePoint, _goal: &EdgePoint) -> f64 {
+            0.0
+        }
+    }
+
+    fn edge(id: i64, source: i64, target: i64, length: f64) -> Edge {
+        Edge {
+            id,
+            source,
+            target,
+            lon1: 0.0,
+            lat1: 0.0,
+            lon2: 0.0,
+            lat2: 0.0,
+            way_id: id,
+            length,
+            tags: sqlx::types::Json(HashMap::new()),
+            road_work_start: vec![],
+            road_work_end: vec![],
+            report_score: vec![],
+            report_date: vec![],
+            in_bicycle_route: false,
+            in_route_verte: false,
+            snow: false,
+            elevation_start: None,
+            elevation_end: None,
+            source_node_tags: None,
+            target_node_tags: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restriction_at_meeting_point() {
+        // 1 - 2 - 3 - 4 tout droit, ou le détour 2 - 5 - 4. Le cul-de-sac 3 - 6, sans
+        // demi-tour possible, fait se rencontrer les deux recherches au noeud 3, où on ne
+        // peut pas aller tout droit.
+        let mut builder = GraphBuilder::default();
+        // Les longueurs sont distinctes: deux états de même score s'écrasent dans la file
+        builder.push(edge(1, 1, 2, 101.0));
+        builder.push(edge(2, 2, 3, 103.0));
+        builder.push(edge(3, 3, 4, 107.0));
+        builder.push(edge(4, 3, 6, 1.0));
+        builder.push(edge(5, 2, 5, 509.0));
+        builder.push(edge(6, 5, 4, 521.0));
+        let graph = GraphView::new(ARc::new(builder.build()));
+        let mut restrictions = TurnRestrictions::default();
+        restrictions.insert("no_straight_on", 2, 3, 3);
+        restrictions.insert("no_u_turn", 4, 6, 4);
+
+        let conn = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/veloinfo")
+            .unwrap();
+        let points = Edge::a_star_bidirectional_in(
+            Some(&graph),
+            &restrictions,
+            (1, 4),
+            Box::new(HLength),
+            &conn,
+            None,
+            true,
+        )
+        .await;
+        let nodes: Vec<i64> = points.iter().map(|point| point.node_id).collect();
+        assert_eq!(nodes, vec![1, 2, 5, 4]);
+    }
 }
@@ -0,0 +1,633 @@
+//! Graphe routier en mémoire, chargé au démarrage depuis la table `edge`.
+//! Les arcs sortants de chaque noeud sont rangés en CSR (compressed sparse row):
+//! ceux du noeud `i` sont `adjacency[offsets[i]..offsets[i + 1]]`.
+//! Les attributs des edges sont lus une seule fois des tags. L'état dynamique
+//! (signalements, entraves, neige) est une couche à part, rafraîchie sans reconstruire le graphe.
+
+use std::collections::HashMap;
+use std::sync::{Arc as ARc, RwLock};
+
+use chrono::NaiveDate;
+use futures::TryStreamExt;
+use lazy_static::lazy_static;
+use sqlx::Postgres;
+
+use super::ch;
+use super::edge::{Edge, EdgeOverlay, EdgePoint, Highway, SourceOrTarget};
+use super::report::EdgeReport;
+use super::road_work::RoadworkPeriod;
+use super::snapshot::{self, SNAPSHOT_PATH};
+use super::turn_restriction;
+use crate::utils::junction::arterial_rank;
+
+/// id de l'edge, dates de début et dates de fin des entraves qui le touchent
+type RoadworkRow = (i64, Vec<Option<NaiveDate>>, Vec<Option<NaiveDate>>);
+type ReportRow = (i64, Vec<f64>, Vec<NaiveDate>);
+
+/// Edges du graphe, sans état dynamique
+pub const GRAPH_EDGES_QUERY: &str = r#"SELECT
+    e.id,
+    e.source,
+    e.target,
+    ST_X(ST_Transform(ST_SetSRID(ST_MakePoint(e.x1, e.y1), 3857), 4326)) as lon1,
+    ST_Y(ST_Transform(ST_SetSRID(ST_MakePoint(e.x1, e.y1), 3857), 4326)) as lat1,
+    ST_X(ST_Transform(ST_SetSRID(ST_MakePoint(e.x2, e.y2), 3857), 4326)) as lon2,
+    ST_Y(ST_Transform(ST_SetSRID(ST_MakePoint(e.x2, e.y2), 3857), 4326)) as lat2,
+    e.tags,
+    e.way_id,
+    e.in_bicycle_route,
+    e.in_route_verte,
+    st_length(ST_Transform(e.geom, 4326)::geography) as length,
+    '{}'::date[] as road_work_start,
+    '{}'::date[] as road_work_end,
+    '{}'::float8[] as report_score,
+    '{}'::date[] as report_date,
+    false as snow,
+    e.elevation_start,
+    e.elevation_end,
+    e.source_node_tags,
+    e.target_node_tags
+FROM edge e"#;
+
+lazy_static! {
+    static ref GRAPH: RwLock<Option<ARc<Graph>>> = RwLock::new(None);
+}
+
+pub struct Graph {
+    /// node_id OSM -> index du noeud dans `offsets`
+    node_index: HashMap<i64, u32>,
+    offsets: Vec<u32>,
+    /// Arcs sortants. Un arc est l'index `i` de son edge suivi d'un bit de sens:
+    /// `2 * i` de source vers target, `2 * i + 1` de target vers source
+    adjacency: Vec<u32>,
+    /// Noeud de départ de chaque arc, `u32::MAX` pour le second arc d'une boucle
+    tails: Vec<u32>,
+    /// Un seul `EdgePoint` par edge, de source vers target; l'autre sens est résolu à la lecture
+    edges: Vec<ARc<EdgePoint>>,
+    /// id de l'edge -> `i`
+    edge_index: HashMap<i64, u32>,
+    /// Edges auxquels l'état dynamique a été appliqué, par index d'edge
+    overlay: RwLock<ARc<HashMap<u32, ARc<EdgePoint>>>>,
+}
+
+#[derive(Default)]
+pub struct GraphBuilder {
+    node_index: HashMap<i64, u32>,
+    /// Noeud de départ de chaque arc
+    arc_nodes: Vec<u32>,
+    edges: Vec<EdgePoint>,
+    edge_index: HashMap<i64, u32>,
+}
+
+impl GraphBuilder {
+    fn node(&mut self, node_id: i64) -> u32 {
+        let next = self.node_index.len() as u32;
+        *self.node_index.entry(node_id).or_insert(next)
+    }
+
+    /// Ajoute un edge lu sans état dynamique
+    pub fn push(&mut self, edge: Edge) {
+        let source = self.node(edge.source);
+        let target = self.node(edge.target);
+        self.edge_index.insert(edge.id, self.edges.len() as u32);
+        // Comme dans `get_neighbors`: on quitte `source` en direction de `target`
+        self.edges
+            .push((ARc::new(edge), SourceOrTarget::Target).into());
+        self.arc_nodes.push(source);
+        // Une boucle n'a qu'un seul arc sortant
+        self.arc_nodes
+            .push(if source == target { u32::MAX } else { target });
+    }
+
+    pub fn build(mut self) -> Graph {
+        let node_count = self.node_index.len();
+        let mut offsets = vec![0u32; node_count + 1];
+        for node in self.arc_nodes.iter().filter(|node| **node != u32::MAX) {
+            offsets[*node as usize + 1] += 1;
+        }
+        for i in 0..node_count {
+            offsets[i + 1] += offsets[i];
+        }
+        // Artère la plus importante à chaque noeud, pour le coût des intersections
+        let mut junctions: Vec<Option<Highway>> = vec![None; node_count];
+        for (arc, node) in self.arc_nodes.iter().enumerate() {
+            if *node == u32::MAX {
+                continue;
+            }
+            let highway = &self.edges[arc / 2].highway;
+            let junction = &mut junctions[*node as usize];
+            if arterial_rank(highway.as_ref()) > arterial_rank(junction.as_ref()) {
+                *junction = highway.clone();
+            }
+        }
+        for (i, edge) in self.edges.iter_mut().enumerate() {
+            let source = self.arc_nodes[2 * i];
+            let target = match self.arc_nodes[2 * i + 1] {
+                u32::MAX => source,
+                target => target,
+            };
+            edge.junction_source = junctions[source as usize].clone();
+            edge.junction_target = junctions[target as usize].clone();
+        }
+
+        let mut next = offsets.clone();
+        let mut adjacency = vec![0u32; offsets[node_count] as usize];
+        for (arc, node) in self.arc_nodes.iter().enumerate() {
+            if *node == u32::MAX {
+                continue;
+            }
+            adjacency[next[*node as usize] as usize] = arc as u32;
+            next[*node as usize] += 1;
+        }
+        Graph {
+            node_index: self.node_index,
+            offsets,
+            adjacency,
+            tails: self.arc_nodes,
+            edges: self.edges.into_iter().map(ARc::new).collect(),
+            edge_index: self.edge_index,
+            overlay: RwLock::new(ARc::new(HashMap::new())),
+        }
+    }
+}
+
+impl Graph {
+    pub fn node_count(&self) -> usize {
+        self.node_index.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edge_index.len()
+    }
+
+    pub fn arc_count(&self) -> usize {
+        self.tails.len()
+    }
+
+    /// Index du noeud `node_id` dans le graphe
+    pub fn node(&self, node_id: i64) -> Option<u32> {
+        self.node_index.get(&node_id).copied()
+    }
+
+    /// Noeuds de départ et d'arrivée d'un arc, None pour une boucle
+    pub fn arc_nodes(&self, arc: u32) -> Option<(u32, u32)> {
+        let tail = self.tails[arc as usize];
+        let head = self.tails[(arc ^ 1) as usize];
+        if tail == u32::MAX || head == u32::MAX || tail == head {
+            return None;
+        }
+        Some((tail, head))
+    }
+
+    /// Longitude et latitude de chaque noeud, par index
+    pub fn node_coords(&self) -> Vec<(f64, f64)> {
+        let mut coords = vec![(0.0, 0.0); self.node_count()];
+        for (i, edge) in self.edges.iter().enumerate() {
+            let ends = [
+                (self.tails[2 * i], (edge.lon1, edge.lat1)),
+                (self.tails[2 * i + 1], (edge.lon2, edge.lat2)),
+            ];
+            for (tail, coord) in ends {
+                if tail != u32::MAX {
+                    coords[tail as usize] = coord;
+                }
+            }
+        }
+        coords
+    }
+
+    /// État dynamique courant, à passer à `arc`
+    pub fn overlay(&self) -> ARc<HashMap<u32, ARc<EdgePoint>>> {
+        self.overlay.read().unwrap().clone()
+    }
+
+    /// Edge d'index `i`, de source vers target, avec l'état dynamique `overlay`
+    fn edge<'a>(&'a self, i: u32, overlay: &'a HashMap<u32, ARc<EdgePoint>>) -> &'a ARc<EdgePoint> {
+        overlay.get(&i).unwrap_or(&self.edges[i as usize])
+    }
+
+    /// Arc d'index `arc`, avec l'état dynamique `overlay`. L'edge est retourné
+    /// si le bit de sens de l'arc est celui de target vers source.
+    pub fn arc(&self, arc: u32, overlay: &HashMap<u32, ARc<EdgePoint>>) -> ARc<EdgePoint> {
+        let edge = self.edge(arc / 2, overlay);
+        if arc & 1 == 0 {
+            edge.clone()
+        } else {
+            ARc::new(edge.reverse())
+        }
+    }
+
+    /// Edges du graphe, de source vers target
+    pub fn edges(&self) -> impl Iterator<Item = &ARc<EdgePoint>> {
+        self.edges.iter()
+    }
+
+    /// Index des arcs qui quittent `node_id`, None si le noeud n'est pas dans le graphe
+    pub fn out_arcs(&self, node_id: i64) -> Option<&[u32]> {
+        let node = self.node(node_id)? as usize;
+        Some(&self.adjacency[self.offsets[node] as usize..self.offsets[node + 1] as usize])
+    }
+
+    /// Arcs qui quittent `node_id`, avec l'état dynamique `overlay`.
+    /// None si le noeud n'est pas dans le graphe.
+    pub fn neighbors<'a>(
+        &'a self,
+        node_id: i64,
+        overlay: &'a HashMap<u32, ARc<EdgePoint>>,
+    ) -> Option<impl Iterator<Item = ARc<EdgePoint>> + 'a> {
+        Some(
+            self.out_arcs(node_id)?
+                .iter()
+                .map(move |arc| self.arc(*arc, overlay)),
+        )
+    }
+
+    /// id des edges qui touchent un des noeuds `node_ids`
+    pub fn edges_at(&self, node_ids: &[i64]) -> Vec<i64> {
+        let mut edge_ids: Vec<i64> = node_ids
+            .iter()
+            .filter_map(|node_id| self.out_arcs(*node_id))
+            .flatten()
+            .map(|arc| self.edges[(*arc / 2) as usize].id)
+            .collect();
+        edge_ids.sort_unstable();
+        edge_ids.dedup();
+        edge_ids
+    }
+
+    /// Applique `overlay` à l'edge `edge_id`, ou le remet dans son état d'origine
+    fn overlay_edge(
+        &self,
+        edges: &mut HashMap<u32, ARc<EdgePoint>>,
+        edge_id: i64,
+        overlay: Option<&EdgeOverlay>,
+    ) {
+        let Some(i) = self.edge_index.get(&edge_id) else {
+            return;
+        };
+        match overlay.filter(|overlay| !overlay.is_empty()) {
+            Some(overlay) => {
+                edges.insert(*i, ARc::new(self.edges[*i as usize].with_overlay(overlay)));
+            }
+            None => {
+                edges.remove(i);
+            }
+        }
+    }
+
+    /// Remplace l'état dynamique de tous les edges par `overlays` (id de l'edge -> état)
+    pub fn set_overlay(&self, overlays: &HashMap<i64, EdgeOverlay>) {
+        let mut edges = HashMap::new();
+        for (edge_id, overlay) in overlays {
+            self.overlay_edge(&mut edges, *edge_id, Some(overlay));
+        }
+        *self.overlay.write().unwrap() = ARc::new(edges);
+    }
+
+    /// Remplace l'état dynamique des edges `edge_ids` seulement; ceux absents de `overlays`
+    /// retrouvent leur état d'origine
+    pub fn update_overlay(&self, edge_ids: &[i64], overlays: &HashMap<i64, EdgeOverlay>) {
+        let mut current = self.overlay.write().unwrap();
+        let mut edges = (**current).clone();
+        for edge_id in edge_ids {
+            self.overlay_edge(&mut edges, *edge_id, overlays.get(edge_id));
+        }
+        *current = ARc::new(edges);
+    }
+
+    pub async fn load(conn: &sqlx::Pool<Postgres>) -> Result<Graph, sqlx::Error> {
+        let mut builder = GraphBuilder::default();
+        let mut edges = sqlx::query_as::<_, Edge>(GRAPH_EDGES_QUERY).fetch(conn);
+        while let Some(edge) = edges.try_next().await? {
+            builder.push(edge);
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Graphe avec l'état dynamique lu une seule fois, pour toute la durée d'une recherche
+pub struct GraphView {
+    graph: ARc<Graph>,
+    overlay: ARc<HashMap<u32, ARc<EdgePoint>>>,
+}
+
+impl GraphView {
+    pub fn new(graph: ARc<Graph>) -> GraphView {
+        GraphView {
+            overlay: graph.overlay(),
+            graph,
+        }
+    }
+
+    /// Graphe chargé, s'il y en a un
+    pub fn current() -> Option<GraphView> {
+        current().map(GraphView::new)
+    }
+
+    pub fn out_arcs(&self, node_id: i64) -> Option<&[u32]> {
+        self.graph.out_arcs(node_id)
+    }
+
+    pub fn arc(&self, arc: u32) -> ARc<EdgePoint> {
+        self.graph.arc(arc, &self.overlay)
+    }
+
+    pub fn neighbors(&self, node_id: i64) -> Option<impl Iterator<Item = ARc<EdgePoint>> + '_> {
+        self.graph.neighbors(node_id, &self.overlay)
+    }
+}
+
+/// État dynamique des edges touchés par une entrave, un signalement ou la neige,
+/// parmi `edge_ids` ou tous si None
+pub async fn load_overlay(
+    conn: &sqlx::Pool<Postgres>,
+    edge_ids: Option<&[i64]>,
+) -> Result<HashMap<i64, EdgeOverlay>, sqlx::Error> {
+    let mut overlays: HashMap<i64, EdgeOverlay> = HashMap::new();
+
+    let road_work: Vec<RoadworkRow> = sqlx::query_as(
+        r#"SELECT e.id, array_agg(w.start_date), array_agg(w.end_date)
+        FROM road_work w
+            JOIN edge e ON ST_Intersects(e.geom, w.geom)
+        WHERE $1::bigint[] IS NULL OR e.id = ANY($1)
+        GROUP BY e.id"#,
+    )
+    .bind(edge_ids)
+    .fetch_all(conn)
+    .await?;
+    for (edge_id, start_dates, end_dates) in road_work {
+        overlays.entry(edge_id).or_default().road_work = start_dates
+            .into_iter()
+            .zip(end_dates)
+            .map(|(start_date, end_date)| RoadworkPeriod {
+                start_date,
+                end_date,
+            })
+            .collect();
+    }
+
+    let reports: Vec<ReportRow> = sqlx::query_as(
+        r#"SELECT e.id, array_agg(r.score),
+            array_agg((r.created_at at time zone 'America/Montreal')::date)
+        FROM report r
+            JOIN edge e ON ST_Intersects(e.geom, r.geom)
+        WHERE r.enabled = true
+            AND ($1::bigint[] IS NULL OR e.id = ANY($1))
+        GROUP BY e.id"#,
+    )
+    .bind(edge_ids)
+    .fetch_all(conn)
+    .await?;
+    for (edge_id, scores, dates) in reports {
+        overlays.entry(edge_id).or_default().reports = scores
+            .into_iter()
+            .zip(dates)
+            .map(|(score, date)| EdgeReport { score, date })
+            .collect();
+    }
+
+    let snow: Vec<i64> = sqlx::query_scalar(
+        r#"SELECT e.id
+        FROM edge e
+            JOIN city_snow csnow ON csnow.city_name = e.city_name
+        WHERE $1::bigint[] IS NULL OR e.id = ANY($1)"#,
+    )
+    .bind(edge_ids)
+    .fetch_all(conn)
+    .await?;
+    for edge_id in snow {
+        overlays.entry(edge_id).or_default().snow = true;
+    }
+
+    Ok(overlays)
+}
+
+/// Graphe chargé, s'il y en a un
+pub fn current() -> Option<ARc<Graph>> {
+    GRAPH.read().unwrap().clone()
+}
+
+/// Enregistre le niveau de stress (`utils::lts`) de chaque way dans `way_lts`, lu par les
+/// tuiles `bike_path`, après un import ou si la table est vide
+async fn store_lts(
+    graph: &Graph,
+    imported: bool,
+    conn: &sqlx::Pool<Postgres>,
+) -> Result<(), sqlx::Error> {
+    if !imported {
+        let empty: bool = sqlx::query_scalar("SELECT NOT EXISTS (SELECT 1 FROM way_lts)")
+            .fetch_one(conn)
+            .await?;
+        if !empty {
+            return Ok(());
+        }
+    }
+    let ways: HashMap<i64, i16> = graph
+        .edges()
+        .map(|edge| (edge.way_id, edge.lts as i16))
+        .collect();
+    let (way_ids, levels): (Vec<i64>, Vec<i16>) = ways.into_iter().unzip();
+    let mut tx = conn.begin().await?;
+    sqlx::query("TRUNCATE way_lts").execute(&mut *tx).await?;
+    sqlx::query(
+        r#"INSERT INTO way_lts (way_id, lts)
+        SELECT * FROM UNNEST($1::bigint[], $2::smallint[])"#,
+    )
+    .bind(&way_ids)
+    .bind(&levels)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+/// Graphe de l'instantané `SNAPSHOT_PATH`, réécrit d'abord s'il manque ou s'il ne
+/// correspond plus au dernier import. Lu de la base si l'instantané est inutilisable.
+/// Vrai si l'instantané a été réécrit.
+async fn load_graph(conn: &sqlx::Pool<Postgres>) -> Result<(Graph, bool), String> {
+    let osm_timestamp = snapshot::osm_timestamp(conn).await;
+    let stale = match snapshot::read_header(SNAPSHOT_PATH) {
+        Ok(header) => osm_timestamp != 0 && header.osm_timestamp != osm_timestamp,
+        Err(_) => true,
+    };
+    if stale {
+        if let Err(e) = snapshot::write_from_db(conn, SNAPSHOT_PATH).await {
+            eprintln!("Error while writing routing graph snapshot: {}", e);
+        }
+    }
+    match snapshot::read(SNAPSHOT_PATH) {
+        Ok((_, graph)) => Ok((graph, stale)),
+        Err(e) => {
+            eprintln!("Error while reading routing graph snapshot: {}", e);
+            let graph = Graph::load(conn).await.map_err(|e| e.to_string())?;
+            Ok((graph, true))
+        }
+    }
+}
+
+/// Charge (ou recharge) le graphe et son état dynamique.
+/// Le graphe précédent reste utilisé pendant le chargement.
+pub async fn reload(conn: &sqlx::Pool<Postgres>) {
+    turn_restriction::reload(conn).await;
+    let (graph, imported) = match load_graph(conn).await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Error while loading routing graph: {}", e);
+            return;
+        }
+    };
+    match load_overlay(conn, None).await {
+        Ok(overlays) => graph.set_overlay(&overlays),
+        Err(e) => eprintln!("Error while loading routing graph overlay: {}", e),
+    }
+    println!(
+        "Routing graph loaded: {} nodes, {} edges",
+        graph.node_count(),
+        graph.edge_count()
+    );
+    if let Err(e) = store_lts(&graph, imported, conn).await {
+        eprintln!("Error while storing way LTS: {}", e);
+    }
+    let graph = ARc::new(graph);
+    *GRAPH.write().unwrap() = Some(graph.clone());
+    ch::rebuild(graph).await;
+}
+
+/// Relit les signalements, entraves et la neige des edges qui touchent `node_ids`,
+/// sans reconstruire le graphe
+pub async fn refresh_overlay(node_ids: &[i64], conn: &sqlx::Pool<Postgres>) {
+    let Some(graph) = current() else {
+        return;
+    };
+    let edge_ids = graph.edges_at(node_ids);
+    if edge_ids.is_empty() {
+        return;
+    }
+    match load_overlay(conn, Some(&edge_ids)).await {
+        Ok(overlays) => {
+            graph.update_overlay(&edge_ids, &overlays);
+            ch::clear_metrics();
+        }
+        Err(e) => eprintln!("Error while refreshing routing graph overlay: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::Cycleway;
+
+    fn edge(id: i64, source: i64, target: i64, tags: &[(&str, &str)]) -> Edge {
+        Edge {
+            id,
+            source,
+            target,
+            lon1: 0.0,
+            lat1: 0.0,
+            lon2: 0.0,
+            lat2: 0.0,
+            way_id: id,
+            length: 100.0,
+            tags: sqlx::types::Json(
+                tags.iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            road_work_start: vec![],
+            road_work_end: vec![],
+            report_score: vec![],
+            report_date: vec![],
+            in_bicycle_route: false,
+            in_route_verte: false,
+            snow: false,
+            elevation_start: None,
+            elevation_end: None,
+            source_node_tags: None,
+            target_node_tags: None,
+        }
+    }
+
+    fn graph() -> Graph {
+        let mut builder = GraphBuilder::default();
+        builder.push(edge(1, 10, 20, &[]));
+        builder.push(edge(
+            2,
+            20,
+            30,
+            &[("cycleway", "track"), ("cycleway:conditional", "no @ snow")],
+        ));
+        builder.push(edge(3, 30, 30, &[]));
+        builder.build()
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let graph = graph();
+        assert_eq!(graph.node_count(), 3);
+        let overlay = graph.overlay();
+        let mut ids: Vec<(i64, i64)> = graph
+            .neighbors(20, &overlay)
+            .unwrap()
+            .map(|arc| (arc.id, arc.get_node_id()))
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![(1, 10), (2, 30)]);
+        // Une boucle ne sort qu'une fois
+        assert_eq!(graph.neighbors(30, &overlay).unwrap().count(), 2);
+        assert!(graph.neighbors(40, &overlay).is_none());
+    }
+
+    #[test]
+    fn test_junctions() {
+        let mut builder = GraphBuilder::default();
+        builder.push(edge(1, 10, 20, &[("highway", "residential")]));
+        builder.push(edge(2, 20, 30, &[("highway", "primary")]));
+        let graph = builder.build();
+        let overlay = graph.overlay();
+        let arc = graph
+            .neighbors(10, &overlay)
+            .unwrap()
+            .find(|arc| arc.id == 1)
+            .unwrap();
+        // La rue résidentielle arrive au noeud 20, sur la primary
+        assert_eq!(arc.junction(), Some(&Highway::Primary));
+        assert_eq!(arc.reverse().junction(), None);
+    }
+
+    #[test]
+    fn test_overlay() {
+        let graph = graph();
+        let mut overlays = HashMap::new();
+        overlays.insert(
+            2,
+            EdgeOverlay {
+                snow: true,
+                ..EdgeOverlay::default()
+            },
+        );
+        graph.set_overlay(&overlays);
+        let track = |graph: &Graph| {
+            let overlay = graph.overlay();
+            let arc = graph
+                .neighbors(20, &overlay)
+                .unwrap()
+                .find(|arc| arc.id == 2)
+                .unwrap()
+                .clone();
+            arc
+        };
+        assert_eq!(track(&graph).cycleway, Some(Cycleway::Snow));
+        // La neige fond: on retrouve la piste d'origine
+        graph.set_overlay(&HashMap::new());
+        assert_eq!(track(&graph).cycleway, Some(Cycleway::Track));
+
+        // Seuls les edges qui touchent le noeud 10 sont relus
+        let edge_ids = graph.edges_at(&[10]);
+        assert_eq!(edge_ids, vec![1]);
+        graph.update_overlay(&edge_ids, &overlays);
+        assert_eq!(track(&graph).cycleway, Some(Cycleway::Track));
+        graph.update_overlay(&graph.edges_at(&[30]), &overlays);
+        assert_eq!(track(&graph).cycleway, Some(Cycleway::Snow));
+        graph.update_overlay(&[2], &HashMap::new());
+        assert_eq!(track(&graph).cycleway, Some(Cycleway::Track));
+    }
+}
@@ -0,0 +1,245 @@
+//! Dangers qui restent sur un itinéraire calculé: signalements actifs, entraves en vigueur
+//! le jour du départ et villes où la neige est au sol. Le calcul d'itinéraire ne fait
+//! que pénaliser ces edges; cette liste permet au cycliste de décider avant de partir.
+//! Les jointures sont celles de `graph::load_overlay`.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::Postgres;
+
+use super::edge::Point;
+use super::road_work::RoadworkPeriod;
+use super::utils::distance_meters;
+
+#[derive(Serialize, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HazardKind {
+    Report,
+    RoadWork,
+    Snow,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    /// id du signalement ou de l'entrave, absent pour la neige
+    pub id: Option<i32>,
+    /// Distance en mètres depuis le départ jusqu'au premier edge touché
+    pub position: f64,
+    pub lng: f64,
+    pub lat: f64,
+    pub score: Option<f64>,
+    /// Dernier commentaire du signalement
+    pub comment: Option<String>,
+    /// Ville enneigée
+    pub name: Option<String>,
+}
+
+/// Premier passage de l'itinéraire sur chaque edge: (distance depuis le départ, lng, lat)
+fn locate_edges(points: &[Point]) -> HashMap<i64, (f64, f64, f64)> {
+    let mut positions = HashMap::new();
+    let mut position = 0.0;
+    for (previous, point) in points.iter().zip(points.iter().skip(1)) {
+        if let Some(edge) = &point.edge {
+            positions
+                .entry(edge.id)
+                .or_insert((position, previous.lng, previous.lat));
+        }
+        position += distance_meters(previous.lat, previous.lng, point.lat, point.lng);
+    }
+    positions
+}
+
+/// Garde, pour chaque danger, l'edge touché le plus tôt sur l'itinéraire
+fn merge(
+    hazards: &mut HashMap<(HazardKind, Option<i32>, Option<String>), Hazard>,
+    positions: &HashMap<i64, (f64, f64, f64)>,
+    edge_id: i64,
+    hazard: Hazard,
+) {
+    let Some(&(position, lng, lat)) = positions.get(&edge_id) else {
+        return;
+    };
+    let key = (hazard.kind, hazard.id, hazard.name.clone());
+    let located = Hazard {
+        position,
+        lng,
+        lat,
+        ..hazard
+    };
+    match hazards.get(&key) {
+        Some(existing) if existing.position <= position => {}
+        _ => {
+            hazards.insert(key, located);
+        }
+    }
+}
+
+/// Dangers rencontrés le long de `points`, dans l'ordre du trajet
+pub async fn along_route(
+    points: &[Point],
+    departure: NaiveDate,
+    conn: &sqlx::Pool<Postgres>,
+) -> Result<Vec<Hazard>, sqlx::Error> {
+    let positions = locate_edges(points);
+    let edge_ids: Vec<i64> = positions.keys().copied().collect();
+    let mut hazards = HashMap::new();
+    if edge_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let reports: Vec<(i64, i32, f64, Option<String>)> = sqlx::query_as(
+        r#"SELECT e.id, r.id, r.score,
+            (SELECT c.comment
+             FROM report_comment c
+             WHERE c.report_id = r.id
+             ORDER BY c.created_at DESC
+             LIMIT 1)
+        FROM report r
+            JOIN edge e ON ST_Intersects(e.geom, r.geom)
+        WHERE r.enabled = true
+            AND e.id = ANY($1)"#,
+    )
+    .bind(&edge_ids)
+    .fetch_all(conn)
+    .await?;
+    for (edge_id, id, score, comment) in reports {
+        let hazard = Hazard {
+            kind: HazardKind::Report,
+            id: Some(id),
+            position: 0.0,
+            lng: 0.0,
+            lat: 0.0,
+            score: Some(score),
+            comment,
+            name: None,
+        };
+        merge(&mut hazards, &positions, edge_id, hazard);
+    }
+
+    let road_work: Vec<(i64, i32, Option<NaiveDate>, Option<NaiveDate>)> = sqlx::query_as(
+        r#"SELECT e.id, w.id, w.start_date, w.end_date
+        FROM road_work w
+            JOIN edge e ON ST_Intersects(e.geom, w.geom)
+        WHERE e.id = ANY($1)"#,
+    )
+    .bind(&edge_ids)
+    .fetch_all(conn)
+    .await?;
+    for (edge_id, id, start_date, end_date) in road_work {
+        let period = RoadworkPeriod {
+            start_date,
+            end_date,
+        };
+        if !period.is_active(departure) {
+            continue;
+        }
+        let hazard = Hazard {
+            kind: HazardKind::RoadWork,
+            id: Some(id),
+            position: 0.0,
+            lng: 0.0,
+            lat: 0.0,
+            score: None,
+            comment: None,
+            name: None,
+        };
+        merge(&mut hazards, &positions, edge_id, hazard);
+    }
+
+    let snow: Vec<(i64, String)> = sqlx::query_as(
+        r#"SELECT e.id, csnow.city_name
+        FROM edge e
+            JOIN city_snow csnow ON csnow.city_name = e.city_name
+        WHERE e.id = ANY($1)"#,
+    )
+    .bind(&edge_ids)
+    .fetch_all(conn)
+    .await?;
+    for (edge_id, city_name) in snow {
+        let hazard = Hazard {
+            kind: HazardKind::Snow,
+            id: None,
+            position: 0.0,
+            lng: 0.0,
+            lat: 0.0,
+            score: None,
+            comment: None,
+            name: Some(city_name),
+        };
+        merge(&mut hazards, &positions, edge_id, hazard);
+    }
+
+    let mut hazards: Vec<Hazard> = hazards.into_values().collect();
+    hazards.sort_by(|a, b| a.position.total_cmp(&b.position));
+    Ok(hazards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::EdgePoint;
+    use std::sync::Arc;
+
+    fn point(lng: f64, edge_id: Option<i64>) -> Point {
+        Point {
+            lng,
+            lat: 45.5,
+            way_id: 0,
+            node_id: 0,
+            length: 0.0,
+            ferry: false,
+            name: None,
+            edge: edge_id.map(|id| {
+                Arc::new(EdgePoint {
+                    id,
+                    ..EdgePoint::default()
+                })
+            }),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let points = [
+            point(-73.6, None),
+            point(-73.59, Some(1)),
+            point(-73.58, Some(2)),
+            point(-73.57, Some(1)),
+        ];
+        let positions = locate_edges(&points);
+        assert_eq!(positions[&1].0, 0.0);
+        assert!(positions[&2].0 > 700.0);
+        assert_eq!(positions[&2].1, -73.59);
+
+        let report = Hazard {
+            kind: HazardKind::Report,
+            id: Some(7),
+            position: 0.0,
+            lng: 0.0,
+            lat: 0.0,
+            score: Some(0.2),
+            comment: None,
+            name: None,
+        };
+        let mut hazards = HashMap::new();
+        merge(&mut hazards, &positions, 2, report.clone());
+        merge(&mut hazards, &positions, 1, report.clone());
+        // Edge absent de l'itinéraire
+        merge(
+            &mut hazards,
+            &positions,
+            3,
+            Hazard {
+                id: Some(8),
+                ..report
+            },
+        );
+        assert_eq!(hazards.len(), 1);
+        let hazard = hazards.values().next().unwrap();
+        assert_eq!(hazard.position, 0.0);
+        assert_eq!(hazard.lng, -73.6);
+    }
+}
@@ -1,9 +1,15 @@
+pub mod city;
 pub mod city_snow;
+pub mod ch;
 pub mod cycleway;
 pub mod edge;
+pub mod graph;
+pub mod hazard;
 pub mod report;
 pub mod report_comment;
 pub mod road_work;
 pub mod search_db;
+pub mod snapshot;
+pub mod turn_restriction;
 pub mod user;
 pub mod utils;
@@ -1,8 +1,33 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate};
 use regex::Regex;
 use sqlx::{Postgres, Row};
 use uuid::Uuid;
 
+/// Le multiplicateur de coût d'un signalement diminue de moitié à chaque période
+const REPORT_HALF_LIFE_DAYS: f64 = 60.0;
+/// Multiplicateur d'un signalement récent de cote 0 (segment impraticable)
+const REPORT_MAX_PENALTY: f64 = 10.0;
+
+/// Signalement actif qui touche un edge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeReport {
+    /// Cote de 0 (impraticable) à 1 (aucun problème)
+    pub score: f64,
+    /// Jour du signalement, à Montréal
+    pub date: NaiveDate,
+}
+
+impl EdgeReport {
+    /// Multiplicateur de coût le jour `date`: il suit la gravité du signalement
+    /// et revient vers 1 à mesure que le signalement vieillit.
+    pub fn penalty(&self, date: NaiveDate) -> f64 {
+        let age = (date - self.date).num_days().max(0) as f64;
+        let decay = 0.5_f64.powf(age / REPORT_HALF_LIFE_DAYS);
+        let severity = 1.0 - self.score.clamp(0.0, 1.0);
+        1.0 + (REPORT_MAX_PENALTY - 1.0) * severity * decay
+    }
+}
+
 #[derive(sqlx::FromRow, Debug)]
 pub struct Report {
     pub id: i32,
@@ -329,3 +354,36 @@ impl From<ReportDb> for Report {
         Report::from(&response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_penalty() {
+        let blocked = EdgeReport {
+            score: 0.0,
+            date: date(2025, 6, 1),
+        };
+        assert_eq!(blocked.penalty(date(2025, 6, 1)), REPORT_MAX_PENALTY);
+        assert_eq!(blocked.penalty(date(2025, 7, 31)), 5.5);
+        assert!(blocked.penalty(date(2026, 6, 1)) < 1.2);
+        // Signalement daté après le départ: aucune décroissance
+        assert_eq!(blocked.penalty(date(2025, 5, 1)), REPORT_MAX_PENALTY);
+
+        let minor = EdgeReport {
+            score: 0.9,
+            ..blocked
+        };
+        assert!((minor.penalty(date(2025, 6, 1)) - 1.9).abs() < 1e-9);
+        let good = EdgeReport {
+            score: 1.0,
+            ..blocked
+        };
+        assert_eq!(good.penalty(date(2025, 6, 1)), 1.0);
+    }
+}
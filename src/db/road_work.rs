@@ -1,14 +1,51 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::America::Montreal;
 use geo::{Geometry as GeoGeometry, LineString, MultiPolygon, Point, Polygon};
 use geojson::{Geometry as GeoJsonGeometry, Value as GeoJsonValue};
 use geozero::wkb;
-use sqlx::types::time::Date;
 use sqlx::PgPool;
 
 #[derive(sqlx::FromRow)]
 pub struct Roadwork {
     pub geom: GeoJsonGeometry,
-    pub start_date: Date,
-    pub end_date: Date,
+    /// Une date manquante laisse la période ouverte de ce côté
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+/// Période de validité d'une entrave qui touche un edge
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RoadworkPeriod {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl RoadworkPeriod {
+    /// Vrai si l'entrave est en vigueur le jour `date` (bornes incluses)
+    pub fn is_active(&self, date: NaiveDate) -> bool {
+        self.start_date.is_none_or(|start| start <= date)
+            && self.end_date.is_none_or(|end| date <= end)
+    }
+}
+
+/// Date du jour à Montréal
+pub fn today() -> NaiveDate {
+    Utc::now().with_timezone(&Montreal).date_naive()
+}
+
+/// Lit une date de départ: `2025-06-01`, `2025-06-01T08:00` (heure de Montréal)
+/// ou RFC 3339 avec fuseau horaire (`2025-06-01T08:00:00-04:00`).
+/// Seul le jour est retenu puisque les entraves sont datées au jour près.
+pub fn parse_departure(departure: &str) -> Result<NaiveDate, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(departure) {
+        return Ok(datetime.with_timezone(&Montreal).date_naive());
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(departure, format).ok())
+        .map(|datetime| datetime.date())
+        .or_else(|| NaiveDate::parse_from_str(departure, "%Y-%m-%d").ok())
+        .ok_or(format!("Invalid departure: {}", departure))
 }
 
 impl Roadwork {
@@ -100,3 +137,45 @@ fn convert_geojson_to_geo(geometry: &GeoJsonGeometry) -> Result<GeoGeometry<f64>
         GeoJsonValue::GeometryCollection(_) => Err("Unsupported geometry type GeometryCollection"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_is_active() {
+        let period = RoadworkPeriod {
+            start_date: Some(date(2025, 6, 1)),
+            end_date: Some(date(2025, 6, 30)),
+        };
+        assert!(period.is_active(date(2025, 6, 1)));
+        assert!(period.is_active(date(2025, 6, 30)));
+        assert!(!period.is_active(date(2025, 5, 31)));
+        assert!(!period.is_active(date(2025, 7, 1)));
+        // Sans date de fin, l'entrave reste en vigueur
+        let open = RoadworkPeriod {
+            end_date: None,
+            ..period
+        };
+        assert!(open.is_active(date(2030, 1, 1)));
+    }
+
+    #[test]
+    fn test_parse_departure() {
+        assert_eq!(parse_departure("2025-06-01").unwrap(), date(2025, 6, 1));
+        assert_eq!(
+            parse_departure("2025-06-01T23:30").unwrap(),
+            date(2025, 6, 1)
+        );
+        // 02:00 UTC est encore la veille à Montréal
+        assert_eq!(
+            parse_departure("2025-06-02T02:00:00Z").unwrap(),
+            date(2025, 6, 1)
+        );
+        assert!(parse_departure("demain").is_err());
+    }
+}
@@ -0,0 +1,531 @@
+//! Instantané binaire du graphe de routage, écrit après l'import et mappé en mémoire au démarrage.
+//! Le format est indépendant du serveur pour pouvoir être embarqué dans l'application Android.
+//! Chaque edge et chaque ensemble de tags est à une position connue: on peut les lire
+//! directement dans le fichier mappé, sans le parcourir.
+//!
+//! Toutes les valeurs sont en little-endian.
+//!
+//! En-tête (56 octets):
+//!   magic `VELOGRPH` (8), version u32, crc32 de tout ce qui suit l'en-tête u32,
+//!   horodatage des données OSM i64 (secondes Unix, 0 si inconnu), date de création i64,
+//!   nombre d'edges u64, nombre d'ensembles de tags u64, taille des données de tags u64.
+//!
+//! Section des edges, un enregistrement de 96 octets par edge:
+//!   id, source, target, way_id (i64), lon1, lat1, lon2, lat2, longueur en mètres (f64),
+//!   index de l'ensemble de tags u32,
+//!   index des ensembles de tags des noeuds de départ et d'arrivée u32 (`u32::MAX` si aucun),
+//!   élévation de départ et d'arrivée (i16),
+//!   drapeaux u8 (1: in_bicycle_route, 2: in_route_verte, 4: élévation de départ, 8: élévation d'arrivée),
+//!   7 octets de remplissage.
+//!
+//! Index des ensembles de tags: position u64 de chaque ensemble dans les données de tags,
+//! plus une dernière position égale à leur taille.
+//!
+//! Données de tags: les edges d'un même way partagent leur ensemble de tags,
+//! et ceux qui touchent un même noeud l'ensemble de tags de ce noeud.
+//!   nombre de tags u16, puis pour chaque tag: longueur u16 et octets UTF-8 de la clé, puis de la valeur.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use futures::TryStreamExt;
+use memmap2::Mmap;
+use sqlx::Postgres;
+
+use super::edge::Edge;
+use super::graph::{Graph, GraphBuilder, GRAPH_EDGES_QUERY};
+
+pub const SNAPSHOT_PATH: &str = "graph/routing.graph";
+pub const MAGIC: &[u8; 8] = b"VELOGRPH";
+pub const VERSION: u32 = 2;
+const HEADER_LEN: usize = 56;
+const EDGE_LEN: usize = 96;
+
+const IN_BICYCLE_ROUTE: u8 = 1;
+const IN_ROUTE_VERTE: u8 = 2;
+const HAS_ELEVATION_START: u8 = 4;
+const HAS_ELEVATION_END: u8 = 8;
+const NO_TAG_SET: u32 = u32::MAX;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    pub checksum: u32,
+    pub osm_timestamp: i64,
+    pub created_at: i64,
+    pub edge_count: u64,
+    pub tag_set_count: u64,
+    pub tags_len: u64,
+}
+
+impl Header {
+    fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[0..8].copy_from_slice(MAGIC);
+        bytes[8..12].copy_from_slice(&self.version.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.osm_timestamp.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.created_at.to_le_bytes());
+        bytes[32..40].copy_from_slice(&self.edge_count.to_le_bytes());
+        bytes[40..48].copy_from_slice(&self.tag_set_count.to_le_bytes());
+        bytes[48..56].copy_from_slice(&self.tags_len.to_le_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Header, String> {
+        let mut reader = Reader::new(bytes);
+        if reader.bytes(8)? != MAGIC {
+            return Err("Not a routing graph snapshot".to_string());
+        }
+        let header = Header {
+            version: reader.u32()?,
+            checksum: reader.u32()?,
+            osm_timestamp: reader.i64()?,
+            created_at: reader.i64()?,
+            edge_count: reader.u64()?,
+            tag_set_count: reader.u64()?,
+            tags_len: reader.u64()?,
+        };
+        if header.version != VERSION {
+            return Err(format!(
+                "Unsupported snapshot version {} (expected {})",
+                header.version, VERSION
+            ));
+        }
+        Ok(header)
+    }
+}
+
+/// Lecture séquentielle des valeurs little-endian d'un tampon
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, position: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.position + len;
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or("Truncated snapshot".to_string())?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        Ok(i16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|e| e.to_string())
+    }
+}
+
+fn encode_edge(edge: &Edge, tag_sets: [u32; 3]) -> [u8; EDGE_LEN] {
+    let mut buffer = Vec::with_capacity(EDGE_LEN);
+    for value in [edge.id, edge.source, edge.target, edge.way_id] {
+        buffer.extend_from_slice(&value.to_le_bytes());
+    }
+    for value in [edge.lon1, edge.lat1, edge.lon2, edge.lat2, edge.length] {
+        buffer.extend_from_slice(&value.to_le_bytes());
+    }
+    for tag_set in tag_sets {
+        buffer.extend_from_slice(&tag_set.to_le_bytes());
+    }
+    buffer.extend_from_slice(&edge.elevation_start.unwrap_or(0).to_le_bytes());
+    buffer.extend_from_slice(&edge.elevation_end.unwrap_or(0).to_le_bytes());
+    let mut flags = 0;
+    if edge.in_bicycle_route {
+        flags |= IN_BICYCLE_ROUTE;
+    }
+    if edge.in_route_verte {
+        flags |= IN_ROUTE_VERTE;
+    }
+    if edge.elevation_start.is_some() {
+        flags |= HAS_ELEVATION_START;
+    }
+    if edge.elevation_end.is_some() {
+        flags |= HAS_ELEVATION_END;
+    }
+    buffer.push(flags);
+    buffer.resize(EDGE_LEN, 0);
+    buffer.try_into().unwrap()
+}
+
+fn encode_tags(tags: &HashMap<String, String>) -> Vec<u8> {
+    let mut buffer = vec![];
+    // Les clés et valeurs OSM font au plus 255 caractères
+    let tags: Vec<(&String, &String)> = tags
+        .iter()
+        .filter(|(key, value)| key.len() <= u16::MAX as usize && value.len() <= u16::MAX as usize)
+        .collect();
+    buffer.extend_from_slice(&(tags.len() as u16).to_le_bytes());
+    for (key, value) in tags {
+        for s in [key, value] {
+            buffer.extend_from_slice(&(s.len() as u16).to_le_bytes());
+            buffer.extend_from_slice(s.as_bytes());
+        }
+    }
+    buffer
+}
+
+/// Écriture en continu d'un instantané. Le fichier est écrit à côté de `path`
+/// puis renommé, pour qu'un lecteur ne voie jamais un fichier à moitié écrit.
+pub struct SnapshotWriter {
+    file: BufWriter<File>,
+    path: String,
+    tmp_path: String,
+    hasher: crc32fast::Hasher,
+    header: Header,
+    /// Ensemble de tags de chaque way déjà écrit
+    way_tag_sets: HashMap<i64, u32>,
+    /// Ensemble de tags de chaque noeud déjà écrit
+    node_tag_sets: HashMap<i64, u32>,
+    tag_sets: Vec<Vec<u8>>,
+}
+
+impl SnapshotWriter {
+    pub fn create(path: &str, osm_timestamp: i64) -> Result<SnapshotWriter, String> {
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let tmp_path = format!("{}.tmp", path);
+        let mut file = BufWriter::new(File::create(&tmp_path).map_err(|e| e.to_string())?);
+        // L'en-tête est réécrit par `finish` une fois le checksum connu
+        file.write_all(&[0u8; HEADER_LEN])
+            .map_err(|e| e.to_string())?;
+        Ok(SnapshotWriter {
+            file,
+            path: path.to_string(),
+            tmp_path,
+            hasher: crc32fast::Hasher::new(),
+            header: Header {
+                version: VERSION,
+                checksum: 0,
+                osm_timestamp,
+                created_at: chrono::Utc::now().timestamp(),
+                edge_count: 0,
+                tag_set_count: 0,
+                tags_len: 0,
+            },
+            way_tag_sets: HashMap::new(),
+            node_tag_sets: HashMap::new(),
+            tag_sets: vec![],
+        })
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.hasher.update(bytes);
+        self.file.write_all(bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn push(&mut self, edge: &Edge) -> Result<(), String> {
+        let next = self.tag_sets.len() as u32;
+        let tag_set = *self.way_tag_sets.entry(edge.way_id).or_insert(next);
+        if tag_set == next {
+            self.tag_sets.push(encode_tags(&edge.tags.0));
+        }
+        let mut node_tag_set = |node_id: i64, tags: &Option<sqlx::types::Json<_>>| {
+            let Some(tags) = tags else {
+                return NO_TAG_SET;
+            };
+            let next = self.tag_sets.len() as u32;
+            let tag_set = *self.node_tag_sets.entry(node_id).or_insert(next);
+            if tag_set == next {
+                self.tag_sets.push(encode_tags(&tags.0));
+            }
+            tag_set
+        };
+        let source_tag_set = node_tag_set(edge.source, &edge.source_node_tags);
+        let target_tag_set = node_tag_set(edge.target, &edge.target_node_tags);
+        self.write(&encode_edge(
+            edge,
+            [tag_set, source_tag_set, target_tag_set],
+        ))?;
+        self.header.edge_count += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Header, String> {
+        let tag_sets = std::mem::take(&mut self.tag_sets);
+        let mut position = 0u64;
+        for tag_set in tag_sets.iter() {
+            self.write(&position.to_le_bytes())?;
+            position += tag_set.len() as u64;
+        }
+        self.write(&position.to_le_bytes())?;
+        for tag_set in tag_sets.iter() {
+            self.write(tag_set)?;
+        }
+        self.header.tag_set_count = tag_sets.len() as u64;
+        self.header.tags_len = position;
+        self.header.checksum = self.hasher.clone().finalize();
+        let mut file = self.file.into_inner().map_err(|e| e.to_string())?;
+        file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+        file.write_all(&self.header.encode())
+            .map_err(|e| e.to_string())?;
+        file.sync_all().map_err(|e| e.to_string())?;
+        std::fs::rename(&self.tmp_path, &self.path).map_err(|e| e.to_string())?;
+        Ok(self.header)
+    }
+}
+
+/// En-tête d'un instantané, sans lire ni vérifier le reste du fichier
+pub fn read_header(path: &str) -> Result<Header, String> {
+    let mut bytes = [0u8; HEADER_LEN];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut bytes))
+        .map_err(|e| e.to_string())?;
+    Header::decode(&bytes)
+}
+
+/// Ensemble de tags `index`, lu à sa position dans les données de tags
+fn decode_tags(
+    offsets: &[u8],
+    tags: &[u8],
+    index: usize,
+) -> Result<HashMap<String, String>, String> {
+    let mut reader = Reader::new(&offsets[index * 8..]);
+    let (start, end) = (reader.u64()? as usize, reader.u64()? as usize);
+    let mut reader = Reader::new(
+        tags.get(start..end)
+            .ok_or("Invalid tag set position".to_string())?,
+    );
+    let count = reader.u16()?;
+    let mut tag_set = HashMap::with_capacity(count as usize);
+    for _ in 0..count {
+        let key = reader.string()?;
+        tag_set.insert(key, reader.string()?);
+    }
+    Ok(tag_set)
+}
+
+/// Vérifie et décode un instantané complet
+pub fn decode(bytes: &[u8]) -> Result<(Header, Graph), String> {
+    let header = Header::decode(bytes)?;
+    let payload = &bytes[HEADER_LEN..];
+    if crc32fast::hash(payload) != header.checksum {
+        return Err("Snapshot checksum mismatch".to_string());
+    }
+    let edges_len = header.edge_count as usize * EDGE_LEN;
+    let offsets_len = (header.tag_set_count as usize + 1) * 8;
+    if payload.len() != edges_len + offsets_len + header.tags_len as usize {
+        return Err("Truncated snapshot".to_string());
+    }
+    let (edges, tags) = payload.split_at(edges_len);
+    let (offsets, tags) = tags.split_at(offsets_len);
+    let tag_sets = (0..header.tag_set_count as usize)
+        .map(|index| decode_tags(offsets, tags, index))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut builder = GraphBuilder::default();
+    for record in edges.chunks_exact(EDGE_LEN) {
+        let mut reader = Reader::new(record);
+        let id = reader.i64()?;
+        let source = reader.i64()?;
+        let target = reader.i64()?;
+        let way_id = reader.i64()?;
+        let lon1 = reader.f64()?;
+        let lat1 = reader.f64()?;
+        let lon2 = reader.f64()?;
+        let lat2 = reader.f64()?;
+        let length = reader.f64()?;
+        let tags = tag_sets
+            .get(reader.u32()? as usize)
+            .ok_or("Invalid tag set index".to_string())?;
+        let mut node_tags = || -> Result<_, String> {
+            match reader.u32()? {
+                NO_TAG_SET => Ok(None),
+                i => tag_sets
+                    .get(i as usize)
+                    .map(|tags| Some(sqlx::types::Json(tags.clone())))
+                    .ok_or("Invalid tag set index".to_string()),
+            }
+        };
+        let source_node_tags = node_tags()?;
+        let target_node_tags = node_tags()?;
+        let elevation_start = reader.i16()?;
+        let elevation_end = reader.i16()?;
+        let flags = reader.u8()?;
+        builder.push(Edge {
+            id,
+            source,
+            target,
+            lon1,
+            lat1,
+            lon2,
+            lat2,
+            way_id,
+            length,
+            tags: sqlx::types::Json(tags.clone()),
+            road_work_start: vec![],
+            road_work_end: vec![],
+            report_score: vec![],
+            report_date: vec![],
+            in_bicycle_route: flags & IN_BICYCLE_ROUTE != 0,
+            in_route_verte: flags & IN_ROUTE_VERTE != 0,
+            snow: false,
+            elevation_start: (flags & HAS_ELEVATION_START != 0).then_some(elevation_start),
+            elevation_end: (flags & HAS_ELEVATION_END != 0).then_some(elevation_end),
+            source_node_tags,
+            target_node_tags,
+        });
+    }
+    Ok((header, builder.build()))
+}
+
+/// Mappe en mémoire et décode l'instantané `path`
+pub fn read(path: &str) -> Result<(Header, Graph), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    // SAFETY: le fichier est remplacé par renommage (`SnapshotWriter::finish`), jamais
+    // réécrit sur place: le contenu mappé ne change pas pendant le décodage
+    let mmap = unsafe { Mmap::map(&file) }.map_err(|e| e.to_string())?;
+    decode(&mmap)
+}
+
+/// Horodatage (secondes Unix) des données OSM du dernier import, 0 si inconnu
+pub async fn osm_timestamp(conn: &sqlx::Pool<Postgres>) -> i64 {
+    sqlx::query_scalar::<_, Option<i64>>(
+        "SELECT extract(epoch from osm_timestamp)::bigint FROM import_info LIMIT 1",
+    )
+    .fetch_one(conn)
+    .await
+    .ok()
+    .flatten()
+    .unwrap_or(0)
+}
+
+/// Écrit l'instantané `path` à partir de la table `edge`
+pub async fn write_from_db(conn: &sqlx::Pool<Postgres>, path: &str) -> Result<Header, String> {
+    let mut writer = SnapshotWriter::create(path, osm_timestamp(conn).await)?;
+    let mut edges = sqlx::query_as::<_, Edge>(GRAPH_EDGES_QUERY).fetch(conn);
+    while let Some(edge) = edges.try_next().await.map_err(|e| e.to_string())? {
+        writer.push(&edge)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(id: i64, way_id: i64, elevation_start: Option<i16>) -> Edge {
+        Edge {
+            id,
+            source: id,
+            target: id + 1,
+            lon1: -73.5,
+            lat1: 45.5,
+            lon2: -73.6,
+            lat2: 45.6,
+            way_id,
+            length: 12.5,
+            tags: sqlx::types::Json(HashMap::from([(
+                "highway".to_string(),
+                "cycleway".to_string(),
+            )])),
+            road_work_start: vec![],
+            road_work_end: vec![],
+            report_score: vec![],
+            report_date: vec![],
+            in_bicycle_route: false,
+            in_route_verte: true,
+            snow: false,
+            elevation_start,
+            elevation_end: None,
+            source_node_tags: None,
+            target_node_tags: (id == 1).then(|| {
+                sqlx::types::Json(HashMap::from([(
+                    "barrier".to_string(),
+                    "bollard".to_string(),
+                )]))
+            }),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = std::env::temp_dir().join(format!("routing-{}.graph", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut writer = SnapshotWriter::create(path, 1_700_000_000).unwrap();
+        writer.push(&edge(1, 100, Some(-3))).unwrap();
+        writer.push(&edge(2, 100, None)).unwrap();
+        writer.push(&edge(3, 200, None)).unwrap();
+        let header = writer.finish().unwrap();
+        assert_eq!(header.edge_count, 3);
+        // Les deux edges du way 100 partagent leurs tags, plus les tags du noeud 2
+        assert_eq!(header.tag_set_count, 3);
+        assert_eq!(read_header(path).unwrap(), header);
+        // Enregistrements de taille fixe, puis l'index et les données des tags
+        assert_eq!(
+            std::fs::metadata(path).unwrap().len(),
+            (HEADER_LEN + 3 * EDGE_LEN + 4 * 8) as u64 + header.tags_len
+        );
+
+        let (read_header, graph) = read(path).unwrap();
+        assert_eq!(read_header.osm_timestamp, 1_700_000_000);
+        assert_eq!(graph.edge_count(), 3);
+        let overlay = graph.overlay();
+        let arc = graph.neighbors(1, &overlay).unwrap().next().unwrap();
+        assert_eq!(arc.elevation_start, Some(-3));
+        assert!(arc.in_route_verte);
+        assert_eq!(arc.highway, Some(crate::db::edge::Highway::Cycleway));
+        assert_eq!(
+            arc.target_node.as_ref().and_then(|node| node.barrier),
+            Some(crate::db::edge::Barrier::Bollard)
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_checksum() {
+        let path = std::env::temp_dir().join(format!("corrupt-{}.graph", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut writer = SnapshotWriter::create(path, 0).unwrap();
+        writer.push(&edge(1, 100, None)).unwrap();
+        writer.finish().unwrap();
+        let mut bytes = std::fs::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(decode(&bytes).is_err());
+        bytes[8] = 99;
+        assert!(decode(&bytes).is_err());
+    }
+}
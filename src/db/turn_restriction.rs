@@ -0,0 +1,147 @@
+//! Restrictions de virage OSM (relations `type=restriction`) qui s'appliquent aux vélos,
+//! importées dans la table `turn_restriction` par import.lua.
+//! Les recherches avancent d'arc en arc: un virage est l'arrivée à un noeud par un arc
+//! suivie du départ par un autre, ce qui suffit pour tenir compte du way d'arrivée.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc as ARc, RwLock};
+
+use lazy_static::lazy_static;
+use sqlx::Postgres;
+
+use super::edge::EdgePoint;
+
+lazy_static! {
+    static ref RESTRICTIONS: RwLock<ARc<TurnRestrictions>> =
+        RwLock::new(ARc::new(TurnRestrictions::default()));
+}
+
+#[derive(Debug, Default)]
+pub struct TurnRestrictions {
+    /// (from_way, via_node, to_way) interdits (`no_*`)
+    forbidden: HashSet<(i64, i64, i64)>,
+    /// (from_way, via_node) -> seuls ways permis à la sortie (`only_*`)
+    only: HashMap<(i64, i64), Vec<i64>>,
+}
+
+impl TurnRestrictions {
+    pub fn insert(&mut self, restriction: &str, from_way: i64, via_node: i64, to_way: i64) {
+        if restriction.starts_with("only_") {
+            self.only
+                .entry((from_way, via_node))
+                .or_default()
+                .push(to_way);
+        } else if restriction.starts_with("no_") {
+            self.forbidden.insert((from_way, via_node, to_way));
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.forbidden.len() + self.only.len()
+    }
+
+    /// Vrai si on peut arriver à `via_node` par `from` et repartir par `to`
+    pub fn allows(&self, via_node: i64, from: &EdgePoint, to: &EdgePoint) -> bool {
+        if let Some(to_ways) = self.only.get(&(from.way_id, via_node)) {
+            if !to_ways.contains(&to.way_id) {
+                return false;
+            }
+        }
+        if !self.forbidden.contains(&(from.way_id, via_node, to.way_id)) {
+            return true;
+        }
+        // Sur un même way (no_u_turn), seul le demi-tour par le même edge est interdit:
+        // continuer tout droit quand le noeud est au milieu du way reste permis
+        from.way_id == to.way_id && from.id != to.id
+    }
+
+    /// Vrai si aucun virage de `path` (sous la forme retournée par `a_star_bidirectional`)
+    /// n'est interdit. Le premier élément ne fait que situer le départ.
+    pub fn allows_path(&self, path: &[ARc<EdgePoint>]) -> bool {
+        path.iter()
+            .skip(1)
+            .zip(path.iter().skip(2))
+            .all(|(from, to)| self.allows(from.get_node_id(), from, to))
+    }
+
+    pub async fn load(conn: &sqlx::Pool<Postgres>) -> Result<TurnRestrictions, sqlx::Error> {
+        let rows: Vec<(String, i64, i64, i64)> = sqlx::query_as(
+            r#"SELECT restriction, from_way, via_node, to_way
+            FROM turn_restriction"#,
+        )
+        .fetch_all(conn)
+        .await?;
+        let mut restrictions = TurnRestrictions::default();
+        for (restriction, from_way, via_node, to_way) in rows {
+            restrictions.insert(&restriction, from_way, via_node, to_way);
+        }
+        Ok(restrictions)
+    }
+}
+
+/// Restrictions chargées, vides tant qu'elles ne sont pas lues
+pub fn current() -> ARc<TurnRestrictions> {
+    RESTRICTIONS.read().unwrap().clone()
+}
+
+pub async fn reload(conn: &sqlx::Pool<Postgres>) {
+    match TurnRestrictions::load(conn).await {
+        Ok(restrictions) => {
+            println!("Turn restrictions loaded: {}", restrictions.count());
+            *RESTRICTIONS.write().unwrap() = ARc::new(restrictions);
+        }
+        Err(e) => eprintln!("Error while loading turn restrictions: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::SourceOrTarget;
+
+    /// Arc du way `way_id` qui arrive au noeud `node`
+    fn arc(id: i64, way_id: i64, node: i64) -> EdgePoint {
+        EdgePoint {
+            id,
+            way_id,
+            source: node - 1,
+            target: node,
+            direction: SourceOrTarget::Target,
+            ..EdgePoint::default()
+        }
+    }
+
+    #[test]
+    fn test_allows() {
+        let mut restrictions = TurnRestrictions::default();
+        restrictions.insert("no_left_turn", 1, 100, 2);
+        restrictions.insert("only_straight_on", 3, 200, 4);
+        restrictions.insert("no_u_turn", 5, 300, 5);
+
+        assert!(!restrictions.allows(100, &arc(1, 1, 100), &arc(2, 2, 101)));
+        assert!(restrictions.allows(100, &arc(1, 1, 100), &arc(3, 3, 101)));
+        // La restriction ne vaut qu'à son noeud
+        assert!(restrictions.allows(101, &arc(1, 1, 101), &arc(2, 2, 102)));
+
+        assert!(restrictions.allows(200, &arc(1, 3, 200), &arc(2, 4, 201)));
+        assert!(!restrictions.allows(200, &arc(1, 3, 200), &arc(2, 6, 201)));
+
+        assert!(!restrictions.allows(300, &arc(7, 5, 300), &arc(7, 5, 299)));
+        assert!(restrictions.allows(300, &arc(7, 5, 300), &arc(8, 5, 301)));
+    }
+
+    #[test]
+    fn test_allows_path() {
+        let mut restrictions = TurnRestrictions::default();
+        restrictions.insert("no_left_turn", 1, 100, 2);
+        let path = |second_way: i64| {
+            vec![
+                ARc::new(arc(1, 1, 99)),
+                ARc::new(arc(1, 1, 100)),
+                ARc::new(arc(2, second_way, 101)),
+            ]
+        };
+        assert!(!restrictions.allows_path(&path(2)));
+        assert!(restrictions.allows_path(&path(3)));
+    }
+}
@@ -43,3 +43,35 @@ pub fn distance_meters(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
 
     RAYON_TERRE * c * 1000.0 // en mètres
 }
+
+/// Cap initial (en degrés, 0 = nord, sens horaire) pour aller du point 1 au point 2
+pub fn bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let lat1_rad = lat1.to_radians();
+    let lat2_rad = lat2.to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+
+    let y = d_lon.sin() * lat2_rad.cos();
+    let x = lat1_rad.cos() * lat2_rad.sin() - lat1_rad.sin() * lat2_rad.cos() * d_lon.cos();
+
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+/// Point atteint en partant de (lat, lon) selon le cap `bearing` (degrés) sur `distance` mètres.
+/// Retourne (lat, lon).
+pub fn destination(lat: f64, lon: f64, bearing: f64, distance: f64) -> (f64, f64) {
+    const RAYON_TERRE: f64 = 6371000.0; // en mètres
+
+    let angular = distance / RAYON_TERRE;
+    let bearing_rad = bearing.to_radians();
+    let lat1_rad = lat.to_radians();
+    let lon1_rad = lon.to_radians();
+
+    let lat2_rad = (lat1_rad.sin() * angular.cos()
+        + lat1_rad.cos() * angular.sin() * bearing_rad.cos())
+    .asin();
+    let lon2_rad = lon1_rad
+        + (bearing_rad.sin() * angular.sin() * lat1_rad.cos())
+            .atan2(angular.cos() - lat1_rad.sin() * lat2_rad.sin());
+
+    (lat2_rad.to_degrees(), lon2_rad.to_degrees())
+}
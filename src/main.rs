@@ -4,8 +4,11 @@ use crate::auth::logout;
 use crate::component::bike_path::bike_path;
 use crate::component::bike_path::bike_path_mvt;
 use crate::component::info_panel::info_panel_up;
+use crate::component::isochrone::isochrone;
 use crate::component::photo_scroll::photo_scroll;
 use crate::component::point_panel::point_panel_lng_lat;
+use crate::component::round_trip::round_trip;
+use crate::component::route_export::route_export;
 use crate::component::route_panel::recalculate_route;
 use crate::component::route_verte::route_verte;
 use crate::component::route_verte::route_verte_mvt;
@@ -34,7 +37,9 @@ use axum::http::HeaderValue;
 use axum::routing::post;
 use axum::routing::{get, Router};
 use component::route_panel::route;
+use component::route_panel::route_via;
 use component::style::style;
+use component::touring::touring;
 use db::city_snow::{city_snow_mvt, post_city_snow};
 use db::edge::Edge;
 use lazy_static::lazy_static;
@@ -147,6 +152,9 @@ async fn main() {
             .await
             .unwrap();
         sched_road_work.start().await.unwrap();
+    } else {
+        // En développement, on charge seulement le graphe de routage
+        Edge::clear_cache_and_reload(&conn).await;
     }
 
     // Définition du routeur Axum
@@ -195,10 +203,24 @@ async fn main() {
             "/route/{start_lng}/{start_lat}/{end_lgt}/{end_lat}",
             get(route),
         )
+        .route("/route_via/{waypoints}", get(route_via))
         .route(
             "/recalculate_route/{route}/{start_lng}/{start_lat}/{end_lgt}/{end_lat}",
             get(recalculate_route),
         )
+        .route(
+            "/route_export/{route}/{format}/{start_lng}/{start_lat}/{end_lgt}/{end_lat}",
+            get(route_export),
+        )
+        .route(
+            "/round_trip/{route}/{lng}/{lat}/{distance}",
+            get(round_trip),
+        )
+        .route("/isochrone/{route}/{lng}/{lat}", get(isochrone))
+        .route(
+            "/touring/{start_lng}/{start_lat}/{end_lng}/{end_lat}",
+            get(touring),
+        )
         // Divers (scores, photos, style mapbox)
         .route(
             "/report/geom/{report_id}",
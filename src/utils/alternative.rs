@@ -0,0 +1,272 @@
+//! Génération d'un itinéraire alternatif par la méthode des pénalités:
+//! on relance la recherche en pénalisant les edges de l'itinéraire principal
+//! jusqu'à obtenir un tracé qui partage peu de segments avec lui.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use serde::Serialize;
+use sqlx::Postgres;
+
+use crate::db::edge::{Edge, Highway, Point};
+use crate::utils::cost::{CostProfile, HPenalty};
+use crate::utils::elevation::get_elevation_profile;
+
+/// Fraction maximale par défaut de la longueur de l'alternative partagée avec l'itinéraire principal
+pub const DEFAULT_MAX_SHARED: f64 = 0.6;
+/// Facteurs de pénalité essayés successivement
+const PENALTY_FACTORS: [f64; 3] = [2.0, 4.0, 8.0];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    LessClimbing,
+    MoreRouteVerte,
+    FewerMainRoads,
+}
+
+/// Caractéristiques utilisées pour expliquer en quoi l'alternative diffère
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct RouteCharacteristics {
+    /// Dénivelé positif en mètres
+    pub ascent: f64,
+    /// Part de la longueur sur la Route Verte (0 à 1)
+    pub route_verte_share: f64,
+    /// Part de la longueur sur des artères (0 à 1)
+    pub main_road_share: f64,
+}
+
+impl RouteCharacteristics {
+    pub fn from_points(points: &[Point]) -> RouteCharacteristics {
+        let edges = points.iter().filter_map(|point| point.edge.as_ref());
+        let (total, route_verte, main_road) =
+            edges.fold((0.0, 0.0, 0.0), |(total, route_verte, main_road), edge| {
+                let is_main_road = matches!(
+                    edge.highway,
+                    Some(Highway::Primary)
+                        | Some(Highway::Secondary)
+                        | Some(Highway::SecondaryLink)
+                        | Some(Highway::Tertiary)
+                        | Some(Highway::Trunk)
+                );
+                (
+                    total + edge.length,
+                    route_verte
+                        + if edge.in_route_verte {
+                            edge.length
+                        } else {
+                            0.0
+                        },
+                    main_road + if is_main_road { edge.length } else { 0.0 },
+                )
+            });
+        let share = |length: f64| if total > 0.0 { length / total } else { 0.0 };
+        RouteCharacteristics {
+            ascent: get_elevation_profile(points).ascent,
+            route_verte_share: share(route_verte),
+            main_road_share: share(main_road),
+        }
+    }
+
+    /// Raisons pour lesquelles `self` (l'alternative) est différente de `primary`
+    pub fn reasons(&self, primary: &RouteCharacteristics) -> Vec<Reason> {
+        let mut reasons = vec![];
+        if primary.ascent - self.ascent >= 10.0 && self.ascent < primary.ascent * 0.9 {
+            reasons.push(Reason::LessClimbing);
+        }
+        if self.route_verte_share - primary.route_verte_share >= 0.05 {
+            reasons.push(Reason::MoreRouteVerte);
+        }
+        if primary.main_road_share - self.main_road_share >= 0.05 {
+            reasons.push(Reason::FewerMainRoads);
+        }
+        reasons
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Alternative {
+    #[serde(skip)]
+    pub points: Vec<Point>,
+    /// Fraction de la longueur partagée avec l'itinéraire principal
+    pub shared: f64,
+    pub reasons: Vec<Reason>,
+    pub characteristics: RouteCharacteristics,
+}
+
+fn edge_ids(points: &[Point]) -> HashSet<i64> {
+    points
+        .iter()
+        .filter_map(|point| point.edge.as_ref().map(|edge| edge.id))
+        .collect()
+}
+
+/// Fraction de la longueur de `points` qui emprunte des edges de `primary_ids`
+pub fn shared_fraction(points: &[Point], primary_ids: &HashSet<i64>) -> f64 {
+    let (shared, total) = points.iter().filter_map(|point| point.edge.as_ref()).fold(
+        (0.0, 0.0),
+        |(shared, total), edge| {
+            if primary_ids.contains(&edge.id) {
+                (shared + edge.length, total + edge.length)
+            } else {
+                (shared, total + edge.length)
+            }
+        },
+    );
+    if total > 0.0 {
+        shared / total
+    } else {
+        1.0
+    }
+}
+
+impl Alternative {
+    fn new(points: Vec<Point>, primary: &[Point]) -> Alternative {
+        let characteristics = RouteCharacteristics::from_points(&points);
+        Alternative {
+            shared: shared_fraction(&points, &edge_ids(primary)),
+            reasons: characteristics.reasons(&RouteCharacteristics::from_points(primary)),
+            characteristics,
+            points,
+        }
+    }
+}
+
+/// Tracé selon `profile` qui partage moins de `max_shared` de sa longueur avec `primary`
+async fn penalized_route(
+    start_node_id: i64,
+    end_node_id: i64,
+    primary: &[Point],
+    profile: &CostProfile,
+    max_shared: f64,
+    allow_ferry: bool,
+    conn: &sqlx::Pool<Postgres>,
+) -> Option<Vec<Point>> {
+    let primary_ids = edge_ids(primary);
+    let mut penalized = primary_ids.clone();
+    for factor in PENALTY_FACTORS {
+        let h = HPenalty {
+            inner: profile.h(),
+            penalized: penalized.clone(),
+            factor,
+        };
+        let points = Edge::a_star_bidirectional(
+            start_node_id,
+            end_node_id,
+            Box::new(h),
+            conn,
+            None,
+            allow_ferry,
+        )
+        .await;
+        if points.is_empty() {
+            return None;
+        }
+        if shared_fraction(&points, &primary_ids) < max_shared {
+            return Some(points);
+        }
+        // Le plateau partagé est aussi pénalisé à la prochaine tentative
+        penalized.extend(edge_ids(&points));
+    }
+    None
+}
+
+/// Cherche un itinéraire, selon `profile`, qui partage moins de `max_shared` de sa longueur avec `primary`.
+pub async fn find_alternative(
+    start_node_id: i64,
+    end_node_id: i64,
+    primary: &[Point],
+    profile: &CostProfile,
+    max_shared: f64,
+    allow_ferry: bool,
+    conn: &sqlx::Pool<Postgres>,
+) -> Option<Alternative> {
+    let points = penalized_route(
+        start_node_id,
+        end_node_id,
+        primary,
+        profile,
+        max_shared,
+        allow_ferry,
+        conn,
+    )
+    .await?;
+    Some(Alternative::new(points, primary))
+}
+
+/// Alternative à un itinéraire en plusieurs tronçons (`legs`, entre les noeuds `node_ids`
+/// consécutifs). Un tronçon sans alternative garde son tracé principal; l'ensemble doit
+/// partager moins de `max_shared` de sa longueur avec l'itinéraire principal.
+pub async fn find_alternative_via(
+    node_ids: &[i64],
+    legs: &[Vec<Point>],
+    profile: &CostProfile,
+    max_shared: f64,
+    allow_ferry: bool,
+    conn: &sqlx::Pool<Postgres>,
+) -> Option<Alternative> {
+    let mut points: Vec<Point> = vec![];
+    let mut primary: Vec<Point> = vec![];
+    for ((start, end), leg) in node_ids.iter().tuple_windows().zip(legs) {
+        let alternative_leg =
+            penalized_route(*start, *end, leg, profile, max_shared, allow_ferry, conn)
+                .await
+                .unwrap_or_else(|| leg.clone());
+        // Le premier point d'un tronçon est le dernier point du tronçon précédent
+        let skip = usize::from(!points.is_empty());
+        points.extend(alternative_leg.into_iter().skip(skip));
+        primary.extend(leg.iter().skip(skip).cloned());
+    }
+    let alternative = Alternative::new(points, &primary);
+    (alternative.shared < max_shared).then_some(alternative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::EdgePoint;
+    use std::sync::Arc;
+
+    fn point(id: i64, length: f64) -> Point {
+        Point {
+            lng: 0.0,
+            lat: 0.0,
+            way_id: 0,
+            node_id: 0,
+            length,
+            ferry: false,
+            name: None,
+            edge: Some(Arc::new(EdgePoint {
+                id,
+                length,
+                ..EdgePoint::default()
+            })),
+        }
+    }
+
+    #[test]
+    fn test_shared_fraction() {
+        let primary = vec![point(1, 100.0), point(2, 100.0), point(3, 100.0)];
+        let alternative = vec![point(1, 100.0), point(4, 200.0), point(3, 100.0)];
+        let shared = shared_fraction(&alternative, &edge_ids(&primary));
+        assert_eq!(shared, 0.5);
+    }
+
+    #[test]
+    fn test_reasons() {
+        let primary = RouteCharacteristics {
+            ascent: 120.0,
+            route_verte_share: 0.1,
+            main_road_share: 0.4,
+        };
+        let alternative = RouteCharacteristics {
+            ascent: 60.0,
+            route_verte_share: 0.5,
+            main_road_share: 0.38,
+        };
+        assert_eq!(
+            alternative.reasons(&primary),
+            vec![Reason::LessClimbing, Reason::MoreRouteVerte]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Zones à éviter fournies par l'usager (chantier pas encore dans `road_work`,
+//! quartier qu'il préfère contourner). Les edges qui les traversent sont interdits
+//! par `cost::HAvoid`, seulement pour la requête en cours.
+
+use geo::{BoundingRect, Coord, CoordsIter, Intersects, Line, MultiPolygon, Polygon, Rect};
+use geojson::{GeoJson, Value};
+
+use crate::db::edge::EdgePoint;
+
+/// Nombre maximal de polygones et de sommets acceptés dans une requête
+pub const MAX_AVOID_AREAS: usize = 20;
+pub const MAX_AVOID_VERTICES: usize = 2000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvoidAreas {
+    pub areas: MultiPolygon,
+    /// Rectangle englobant, pour écarter rapidement les edges éloignés
+    bbox: Rect,
+}
+
+impl AvoidAreas {
+    pub fn new(areas: MultiPolygon) -> Option<AvoidAreas> {
+        let bbox = areas.bounding_rect()?;
+        Some(AvoidAreas { areas, bbox })
+    }
+
+    /// Lit des polygones GeoJSON (géométrie, feature ou feature collection) en EPSG:4326.
+    /// Seuls les Polygon et MultiPolygon sont acceptés.
+    pub fn parse(geojson: &str) -> Result<AvoidAreas, String> {
+        let geojson = geojson
+            .parse::<GeoJson>()
+            .map_err(|e| format!("Invalid avoid GeoJSON: {}", e))?;
+        let values: Vec<Value> = match geojson {
+            GeoJson::Geometry(geometry) => vec![geometry.value],
+            GeoJson::Feature(feature) => feature.geometry.into_iter().map(|g| g.value).collect(),
+            GeoJson::FeatureCollection(collection) => collection
+                .features
+                .into_iter()
+                .filter_map(|feature| feature.geometry.map(|g| g.value))
+                .collect(),
+        };
+
+        let mut polygons: Vec<Polygon> = vec![];
+        for value in values {
+            match &value {
+                Value::Polygon(_) => polygons.push(
+                    Polygon::try_from(&value).map_err(|e| format!("Invalid avoid area: {}", e))?,
+                ),
+                Value::MultiPolygon(_) => polygons.extend(
+                    MultiPolygon::try_from(&value)
+                        .map_err(|e| format!("Invalid avoid area: {}", e))?,
+                ),
+                _ => {
+                    return Err(format!(
+                        "Invalid avoid area: expected Polygon or MultiPolygon, got {}",
+                        value.type_name()
+                    ))
+                }
+            }
+        }
+        if polygons.is_empty() || polygons.len() > MAX_AVOID_AREAS {
+            return Err(format!(
+                "Expected between 1 and {} avoid areas, got {}",
+                MAX_AVOID_AREAS,
+                polygons.len()
+            ));
+        }
+        let areas = MultiPolygon::new(polygons);
+        let vertices = areas.coords_count();
+        if vertices > MAX_AVOID_VERTICES {
+            return Err(format!(
+                "Too many vertices in avoid areas: {} (max {})",
+                vertices, MAX_AVOID_VERTICES
+            ));
+        }
+        AvoidAreas::new(areas).ok_or("Invalid avoid area: empty polygon".to_string())
+    }
+
+    /// Vrai si le segment de l'edge touche une des zones
+    pub fn intersects_edge(&self, edge: &EdgePoint) -> bool {
+        let line = Line::new(
+            Coord {
+                x: edge.lon1,
+                y: edge.lat1,
+            },
+            Coord {
+                x: edge.lon2,
+                y: edge.lat2,
+            },
+        );
+        self.bbox.intersects(&line) && self.areas.intersects(&line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = r#"{"type": "Polygon", "coordinates": [[[-73.6, 45.5], [-73.5, 45.5], [-73.5, 45.6], [-73.6, 45.6], [-73.6, 45.5]]]}"#;
+
+    fn edge(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> EdgePoint {
+        EdgePoint {
+            lon1,
+            lat1,
+            lon2,
+            lat2,
+            ..EdgePoint::default()
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(AvoidAreas::parse(SQUARE).unwrap().areas.0.len(), 1);
+        let collection = format!(
+            r#"{{"type": "FeatureCollection", "features": [
+                {{"type": "Feature", "properties": null, "geometry": {}}},
+                {{"type": "Feature", "properties": null, "geometry": {}}}
+            ]}}"#,
+            SQUARE, SQUARE
+        );
+        assert_eq!(AvoidAreas::parse(&collection).unwrap().areas.0.len(), 2);
+        assert!(AvoidAreas::parse(r#"{"type": "Point", "coordinates": [-73.6, 45.5]}"#).is_err());
+        assert!(AvoidAreas::parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_intersects_edge() {
+        let areas = AvoidAreas::parse(SQUARE).unwrap();
+        // Entièrement à l'intérieur
+        assert!(areas.intersects_edge(&edge(-73.58, 45.52, -73.57, 45.53)));
+        // Traverse la zone sans y avoir de noeud
+        assert!(areas.intersects_edge(&edge(-73.7, 45.55, -73.4, 45.55)));
+        assert!(!areas.intersects_edge(&edge(-73.7, 45.4, -73.65, 45.45)));
+    }
+}
@@ -1,14 +1,33 @@
 use crate::db::edge::{
-    Access, Bicycle, Cycleway, EdgePoint, Footway, Highway, Oneway, Route, Smoothness,
+    Access, Barrier, Bicycle, Cycleway, EdgePoint, Footway, Highway, Oneway, Route, Smoothness,
     SourceOrTarget, Surface, Tunnel,
 };
+use crate::db::road_work;
 use crate::db::utils::distance_meters;
+use crate::utils::avoid::AvoidAreas;
 use crate::utils::elevation;
+use crate::utils::junction;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc as ARc;
+
+/// Coût d'un edge où le passage est interdit
+pub const FORBIDDEN_COST: f64 = 10000.0;
+/// Largeur minimale en mètres pour un vélo cargo ou une remorque
+const CARGO_MIN_WIDTH: f64 = 1.2;
+/// Largeur minimale en mètres d'un trottoir partagé avec un vélo cargo
+const CARGO_MIN_SIDEWALK_WIDTH: f64 = 2.0;
 
 pub trait H: Send {
     fn get_cost(&self, edge: &EdgePoint, allow_ferry: bool) -> f64;
     fn get_max_point(&self) -> i64;
 
+    /// Coût en mètres équivalents du passage de `from` à `to` au noeud qui les relie
+    fn turn_cost(&self, from: &EdgePoint, to: &EdgePoint) -> f64 {
+        junction::junction_cost(from, to)
+    }
+
     fn h(&self, start_point: &EdgePoint, goal: &EdgePoint) -> f64 {
         let (goal_lon, goal_lat) = if SourceOrTarget::Source == goal.direction {
             (goal.lon1, goal.lat1)
@@ -26,28 +45,29 @@ pub trait H: Send {
     }
 }
 
-pub fn get_h_moyen() -> Box<dyn H> {
-    Box::new(HMoyen {})
-}
-
 #[allow(dead_code)]
 pub fn get_h_bigger_selection() -> Box<dyn H> {
     Box::new(HBiggerSelection {})
 }
 
-pub fn get_h_rapid() -> Box<dyn H> {
-    Box::new(HRapid {})
+pub struct HMoyen {
+    /// Jour du départ, pour ne tenir compte que des entraves en vigueur
+    pub departure: NaiveDate,
 }
 
-pub struct HMoyen {}
-
 impl H for HMoyen {
     fn get_max_point(&self) -> i64 {
         i64::MAX
     }
 
     fn get_cost(&self, edge: &EdgePoint, allow_ferry: bool) -> f64 {
-        get_cost(FastOrSafe::Safe, edge, allow_ferry)
+        get_cost(
+            FastOrSafe::Safe,
+            &DEFAULT_WEIGHTS,
+            edge,
+            allow_ferry,
+            self.departure,
+        )
     }
 }
 
@@ -86,11 +106,19 @@ impl H for HBiggerSelection {
     }
 }
 
-pub struct HRapid {}
+pub struct HRapid {
+    pub departure: NaiveDate,
+}
 
 impl H for HRapid {
     fn get_cost(&self, edge: &EdgePoint, allow_ferry: bool) -> f64 {
-        get_cost(FastOrSafe::Fast, edge, allow_ferry)
+        get_cost(
+            FastOrSafe::Fast,
+            &DEFAULT_WEIGHTS,
+            edge,
+            allow_ferry,
+            self.departure,
+        )
     }
 
     fn get_max_point(&self) -> i64 {
@@ -98,11 +126,364 @@ impl H for HRapid {
     }
 }
 
-enum FastOrSafe {
+/// Multiplie le coût des edges déjà utilisés par un autre itinéraire,
+/// pour forcer la recherche d'une alternative réellement différente.
+pub struct HPenalty {
+    pub inner: Box<dyn H>,
+    pub penalized: HashSet<i64>,
+    pub factor: f64,
+}
+
+impl H for HPenalty {
+    fn get_cost(&self, edge: &EdgePoint, allow_ferry: bool) -> f64 {
+        let cost = self.inner.get_cost(edge, allow_ferry);
+        if self.penalized.contains(&edge.id) {
+            cost * self.factor
+        } else {
+            cost
+        }
+    }
+
+    fn get_max_point(&self) -> i64 {
+        self.inner.get_max_point()
+    }
+
+    fn turn_cost(&self, from: &EdgePoint, to: &EdgePoint) -> f64 {
+        self.inner.turn_cost(from, to)
+    }
+}
+
+/// Interdit les edges qui traversent les zones à éviter de la requête.
+/// Le coût est calculé à chaque expansion: le NEIGHBORS_CACHE partagé n'est pas modifié.
+pub struct HAvoid {
+    pub inner: Box<dyn H>,
+    pub areas: ARc<AvoidAreas>,
+}
+
+impl H for HAvoid {
+    fn get_cost(&self, edge: &EdgePoint, allow_ferry: bool) -> f64 {
+        if self.areas.intersects_edge(edge) {
+            return FORBIDDEN_COST;
+        }
+        self.inner.get_cost(edge, allow_ferry)
+    }
+
+    fn get_max_point(&self) -> i64 {
+        self.inner.get_max_point()
+    }
+
+    fn turn_cost(&self, from: &EdgePoint, to: &EdgePoint) -> f64 {
+        self.inner.turn_cost(from, to)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FastOrSafe {
     Fast,
     Safe,
 }
 
+/// Type de vélo, choisi avec le paramètre `profile` des itinéraires
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BikeType {
+    #[default]
+    Regular,
+    /// Vélo à assistance électrique: les montées ne sont presque plus pénalisées
+    Ebike,
+    /// Vélo cargo ou remorque: pas d'escaliers, de chicanes ni de passages étroits
+    Cargo,
+    /// Vélo de route: évite fortement tout revêtement non asphalté
+    Road,
+    /// Cyclotourisme: préfère fortement le réseau de la Route Verte
+    Touring,
+}
+
+impl BikeType {
+    pub fn parse(profile: Option<&str>) -> Result<BikeType, String> {
+        match profile {
+            None | Some("regular") => Ok(BikeType::Regular),
+            Some("ebike") => Ok(BikeType::Ebike),
+            Some("cargo") => Ok(BikeType::Cargo),
+            Some("road") => Ok(BikeType::Road),
+            Some("touring") => Ok(BikeType::Touring),
+            Some(profile) => Err(format!("Invalid profile: {}", profile)),
+        }
+    }
+
+    /// Poids de départ du type de vélo, avant les préférences de l'usager
+    fn base_weights(&self) -> CostWeights {
+        match self {
+            BikeType::Regular | BikeType::Cargo | BikeType::Touring => DEFAULT_WEIGHTS,
+            BikeType::Ebike => CostWeights {
+                hills: 0.3,
+                ..DEFAULT_WEIGHTS
+            },
+            BikeType::Road => CostWeights {
+                gravel: 10.0,
+                cobblestone: 7.4,
+                ..DEFAULT_WEIGHTS
+            },
+        }
+    }
+
+    /// Règles propres au type de vélo, appliquées au coût de base d'un edge
+    fn adjust_cost(&self, edge: &EdgePoint, cost: f64) -> f64 {
+        match self {
+            BikeType::Regular | BikeType::Ebike => cost,
+            BikeType::Touring => {
+                if edge.in_route_verte {
+                    cost
+                } else {
+                    cost * 4.0
+                }
+            }
+            BikeType::Cargo => {
+                let node = edge.arrival_node();
+                if edge.highway == Some(Highway::Steps)
+                    || edge.barrier == Some(Barrier::Cycle)
+                    || edge
+                        .min_width()
+                        .is_some_and(|width| width < CARGO_MIN_WIDTH)
+                    || node.is_some_and(|node| {
+                        matches!(node.barrier, Some(Barrier::Cycle) | Some(Barrier::Stile))
+                            || node.maxwidth.is_some_and(|width| width < CARGO_MIN_WIDTH)
+                    })
+                {
+                    return FORBIDDEN_COST;
+                }
+                let mut cost = cost;
+                if edge.footway == Some(Footway::Sidewalk) {
+                    if edge
+                        .min_width()
+                        .is_some_and(|width| width < CARGO_MIN_SIDEWALK_WIDTH)
+                    {
+                        return FORBIDDEN_COST;
+                    }
+                    cost *= 2.0;
+                }
+                if edge.bicycle == Some(Bicycle::Dismount) {
+                    cost *= 5.0;
+                }
+                cost
+            }
+            BikeType::Road => {
+                if edge.smoothness == Some(Smoothness::Impassable) {
+                    return FORBIDDEN_COST;
+                }
+                let mut cost = cost;
+                if edge.surface.as_ref().is_some_and(Surface::is_unpaved) {
+                    cost *= 20.0;
+                } else if edge.surface.is_none() && edge.highway == Some(Highway::Path) {
+                    // Un sentier sans revêtement connu est rarement asphalté
+                    cost *= 3.0;
+                }
+                if edge
+                    .smoothness
+                    .is_some_and(|smoothness| smoothness > Smoothness::Intermediate)
+                {
+                    cost *= 10.0;
+                }
+                cost
+            }
+        }
+    }
+}
+
+/// Préférences de l'usager, chacune entre 0 (comportement par défaut) et 1 (effet maximal)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Preferences {
+    pub avoid_hills: f64,
+    pub avoid_gravel: f64,
+    pub prefer_separated: f64,
+    pub tolerate_main_roads: f64,
+}
+
+impl Preferences {
+    pub fn new(
+        avoid_hills: Option<f64>,
+        avoid_gravel: Option<f64>,
+        prefer_separated: Option<f64>,
+        tolerate_main_roads: Option<f64>,
+    ) -> Result<Preferences, String> {
+        let validate = |name: &str, value: Option<f64>| match value {
+            None => Ok(0.0),
+            Some(value) if (0.0..=1.0).contains(&value) => Ok(value),
+            Some(value) => Err(format!(
+                "Invalid {} {}: must be between 0 and 1",
+                name, value
+            )),
+        };
+        Ok(Preferences {
+            avoid_hills: validate("avoid_hills", avoid_hills)?,
+            avoid_gravel: validate("avoid_gravel", avoid_gravel)?,
+            prefer_separated: validate("prefer_separated", prefer_separated)?,
+            tolerate_main_roads: validate("tolerate_main_roads", tolerate_main_roads)?,
+        })
+    }
+}
+
+/// Poids utilisés par `get_cost`. Les valeurs par défaut sont celles des profils `safe` et `fast`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CostWeights {
+    /// Pénalité ajoutée pour le gravier ou un mauvais revêtement sur une piste cyclable
+    pub gravel: f64,
+    /// Pénalité ajoutée pour les pavés sur une rue locale
+    pub cobblestone: f64,
+    /// Coefficients des aménagements non séparés de la circulation
+    pub lane: f64,
+    pub shared_lane: f64,
+    pub share_busway: f64,
+    /// Coefficients des artères
+    pub tertiary: f64,
+    pub secondary: f64,
+    pub primary: f64,
+    pub trunk: f64,
+    /// Exposant appliqué au multiplicateur de pente
+    pub hills: f64,
+}
+
+pub const DEFAULT_WEIGHTS: CostWeights = CostWeights {
+    gravel: 1.0,
+    cobblestone: 3.7,
+    lane: 1.2,
+    shared_lane: 1.3,
+    share_busway: 1.4,
+    tertiary: 1.3,
+    secondary: 1.5,
+    primary: 1.6,
+    trunk: 9.0,
+    hills: 1.0,
+};
+
+impl Default for CostWeights {
+    fn default() -> Self {
+        DEFAULT_WEIGHTS
+    }
+}
+
+impl CostWeights {
+    /// Ajuste les poids du type de vélo selon les préférences de l'usager
+    pub fn from_preferences(bike: BikeType, preferences: &Preferences) -> CostWeights {
+        let base = bike.base_weights();
+        // Amplifie (ou atténue) l'écart d'un coefficient par rapport à 1
+        let scale = |coefficient: f64, factor: f64| 1.0 + (coefficient - 1.0) * factor;
+        let separated = 1.0 + 4.0 * preferences.prefer_separated;
+        let main_roads = 1.0 - 0.8 * preferences.tolerate_main_roads;
+        CostWeights {
+            gravel: base.gravel * (1.0 + 4.0 * preferences.avoid_gravel),
+            cobblestone: base.cobblestone * (1.0 + preferences.avoid_gravel),
+            lane: scale(base.lane, separated),
+            shared_lane: scale(base.shared_lane, separated),
+            share_busway: scale(base.share_busway, separated),
+            tertiary: scale(base.tertiary, main_roads),
+            secondary: scale(base.secondary, main_roads),
+            primary: scale(base.primary, main_roads),
+            trunk: scale(base.trunk, main_roads),
+            hills: base.hills + 2.0 * preferences.avoid_hills,
+        }
+    }
+}
+
+/// Profil de coût résolu pour une requête: type d'itinéraire, poids et zones à éviter
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostProfile {
+    pub route: FastOrSafe,
+    pub bike: BikeType,
+    pub preferences: Preferences,
+    pub weights: CostWeights,
+    #[serde(skip)]
+    pub avoid: Option<ARc<AvoidAreas>>,
+    /// Jour du départ; seules les entraves en vigueur ce jour-là sont pénalisées
+    #[serde(skip)]
+    pub departure: NaiveDate,
+}
+
+impl CostProfile {
+    pub fn new(
+        route: &str,
+        bike: BikeType,
+        preferences: Preferences,
+    ) -> Result<CostProfile, String> {
+        let route = match route {
+            "safe" => FastOrSafe::Safe,
+            "fast" => FastOrSafe::Fast,
+            _ => return Err(format!("Invalid route type: {}", route)),
+        };
+        Ok(CostProfile {
+            route,
+            bike,
+            preferences,
+            weights: CostWeights::from_preferences(bike, &preferences),
+            avoid: None,
+            departure: road_work::today(),
+        })
+    }
+
+    pub fn with_avoid(self, avoid: Option<ARc<AvoidAreas>>) -> CostProfile {
+        CostProfile { avoid, ..self }
+    }
+
+    pub fn with_departure(self, departure: NaiveDate) -> CostProfile {
+        CostProfile { departure, ..self }
+    }
+
+    /// Vélo ordinaire sans préférences: les modèles `HMoyen` et `HRapid` s'appliquent
+    pub fn has_default_weights(&self) -> bool {
+        self.bike == BikeType::Regular && self.weights == DEFAULT_WEIGHTS
+    }
+
+    pub fn h(&self) -> Box<dyn H> {
+        let h: Box<dyn H> = if self.has_default_weights() {
+            match self.route {
+                FastOrSafe::Safe => Box::new(HMoyen {
+                    departure: self.departure,
+                }),
+                FastOrSafe::Fast => Box::new(HRapid {
+                    departure: self.departure,
+                }),
+            }
+        } else {
+            Box::new(HCustom {
+                route: self.route,
+                bike: self.bike,
+                weights: self.weights,
+                departure: self.departure,
+            })
+        };
+        match &self.avoid {
+            Some(areas) => Box::new(HAvoid {
+                inner: h,
+                areas: areas.clone(),
+            }),
+            None => h,
+        }
+    }
+}
+
+/// Modèle de coût avec des poids ajustés par l'usager et les règles du type de vélo
+pub struct HCustom {
+    pub route: FastOrSafe,
+    pub bike: BikeType,
+    pub weights: CostWeights,
+    pub departure: NaiveDate,
+}
+
+impl H for HCustom {
+    fn get_cost(&self, edge: &EdgePoint, allow_ferry: bool) -> f64 {
+        let cost = get_cost(self.route, &self.weights, edge, allow_ferry, self.departure);
+        if cost >= FORBIDDEN_COST {
+            return cost;
+        }
+        self.bike.adjust_cost(edge, cost)
+    }
+
+    fn get_max_point(&self) -> i64 {
+        i64::MAX
+    }
+}
+
 /// Vérifie si un type de cycleway est disponible dans la direction de voyage
 /// Prend en compte: cycleway, cycleway_both, cycleway_left, cycleway_right
 /// et les contraintes de sens (oneway)
@@ -137,18 +518,75 @@ fn has_cycleway_of_type(edge: &EdgePoint, cycleway_type: &Cycleway) -> bool {
     false
 }
 
-fn get_cycleway_cost(edge: &EdgePoint) -> Option<f64> {
+/// Meilleur aménagement cyclable dans la direction de voyage.
+/// Une piste (`highway=cycleway`) compte comme `Cycleway::Track`.
+pub fn get_cycleway(edge: &EdgePoint) -> Option<Cycleway> {
+    if edge.highway == Some(Highway::Cycleway) {
+        return Some(Cycleway::Track);
+    }
+    [
+        Cycleway::Track,
+        Cycleway::Lane,
+        Cycleway::SharedLane,
+        Cycleway::ShareBusway,
+    ]
+    .into_iter()
+    .find(|cycleway| has_cycleway_of_type(edge, cycleway))
+}
+
+/// Type d'infrastructure d'un edge, tel que `get_cost` le distingue
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Infrastructure {
+    Track,
+    Lane,
+    /// Chaussée désignée partagée, avec les autos (`shared_lane`) ou les autobus
+    SharedLane,
+    Residential,
+    Arterial,
+    Ferry,
+    /// Sentiers, trottoirs, escaliers et edges sans classe connue
+    Other,
+}
+
+pub fn get_infrastructure(edge: &EdgePoint) -> Infrastructure {
+    if edge.route == Some(Route::Ferry) {
+        return Infrastructure::Ferry;
+    }
+    match get_cycleway(edge) {
+        Some(Cycleway::Track) => return Infrastructure::Track,
+        Some(Cycleway::Lane) => return Infrastructure::Lane,
+        Some(_) => return Infrastructure::SharedLane,
+        None => {}
+    }
+    if edge.cyclestreet {
+        return Infrastructure::Residential;
+    }
+    match edge.highway {
+        Some(Highway::Residential)
+        | Some(Highway::LivingStreet)
+        | Some(Highway::Unclassified)
+        | Some(Highway::Service) => Infrastructure::Residential,
+        Some(Highway::Tertiary)
+        | Some(Highway::Secondary)
+        | Some(Highway::SecondaryLink)
+        | Some(Highway::Primary)
+        | Some(Highway::Trunk) => Infrastructure::Arterial,
+        _ => Infrastructure::Other,
+    }
+}
+
+fn get_cycleway_cost(edge: &EdgePoint, weights: &CostWeights) -> Option<f64> {
     // Déterminer le type de cycleway et son coefficient
     let coefficient = if edge.highway == Some(Highway::Cycleway) {
         1.0
     } else if has_cycleway_of_type(edge, &Cycleway::Track) {
         1.0
     } else if has_cycleway_of_type(edge, &Cycleway::Lane) {
-        1.2
+        weights.lane
     } else if has_cycleway_of_type(edge, &Cycleway::SharedLane) {
-        1.3
+        weights.shared_lane
     } else if has_cycleway_of_type(edge, &Cycleway::ShareBusway) {
-        1.4
+        weights.share_busway
     } else {
         return None;
     };
@@ -161,9 +599,9 @@ fn get_cycleway_cost(edge: &EdgePoint) -> Option<f64> {
 
     // Conditions exclusives (une seule s'applique)
     if edge.surface == Some(Surface::FineGravel) || edge.surface == Some(Surface::Gravel) {
-        base += 1.0;
+        base += weights.gravel;
     } else if edge.smoothness == Some(Smoothness::Bad) {
-        base += 1.0;
+        base += weights.gravel;
     } else if edge.surface == Some(Surface::Sett) {
         base += 1.0;
     }
@@ -171,7 +609,7 @@ fn get_cycleway_cost(edge: &EdgePoint) -> Option<f64> {
     Some(base * coefficient)
 }
 
-fn get_local_road_cost(edge: &EdgePoint) -> Option<f64> {
+fn get_local_road_cost(edge: &EdgePoint, weights: &CostWeights) -> Option<f64> {
     // Vérifier si c'est un local road et obtenir le coefficient
     let coefficient = if edge.cyclestreet {
         0.9
@@ -180,9 +618,9 @@ fn get_local_road_cost(edge: &EdgePoint) -> Option<f64> {
             Some(Highway::Residential) | Some(Highway::LivingStreet) => 1.0,
             Some(Highway::Unclassified) => 1.2,
             Some(Highway::Service) => 1.2,
-            Some(Highway::Tertiary) => 1.3,
-            Some(Highway::Secondary) | Some(Highway::SecondaryLink) => 1.5,
-            Some(Highway::Primary) => 1.6,
+            Some(Highway::Tertiary) => weights.tertiary,
+            Some(Highway::Secondary) | Some(Highway::SecondaryLink) => weights.secondary,
+            Some(Highway::Primary) => weights.primary,
             _ => return None,
         }
     };
@@ -194,7 +632,7 @@ fn get_local_road_cost(edge: &EdgePoint) -> Option<f64> {
         || edge.surface == Some(Surface::Cobblestone)
         || edge.surface == Some(Surface::UnhewnCobblestone)
     {
-        base += 3.7;
+        base += weights.cobblestone;
     } else if edge.surface == Some(Surface::Chipseal) {
         base += 0.5;
     }
@@ -219,7 +657,44 @@ fn get_local_road_cost(edge: &EdgePoint) -> Option<f64> {
     Some(base * coefficient)
 }
 
-fn get_cost(fast_or_safe: FastOrSafe, edge: &EdgePoint, allow_ferry: bool) -> f64 {
+/// Pénalité en mètres équivalents pour franchir la barrière du noeud d'arrivée de
+/// l'edge, None si un vélo ne peut pas y passer. Sans barrière, les tags d'accès du
+/// noeud visent le way qui le porte (une traversée `bicycle=no` est aussi sur la rue)
+/// et sont ignorés.
+pub fn get_node_penalty(edge: &EdgePoint) -> Option<f64> {
+    let Some(node) = edge.arrival_node().filter(|node| node.barrier.is_some()) else {
+        return Some(0.0);
+    };
+    let bicycle_allowed = matches!(node.bicycle, Some(Bicycle::Yes) | Some(Bicycle::Designated));
+    if node.locked || node.bicycle == Some(Bicycle::No) {
+        return None;
+    }
+    if matches!(node.access, Some(Access::Private) | Some(Access::No)) && !bicycle_allowed {
+        return None;
+    }
+    let mut penalty = match node.barrier {
+        None => 0.0,
+        Some(Barrier::Bollard) => 5.0,
+        Some(Barrier::LiftGate) => 10.0,
+        Some(Barrier::Gate) | Some(Barrier::RaisedKerb) => 20.0,
+        Some(Barrier::Cycle) | Some(Barrier::Other) => 30.0,
+        // Il faut soulever le vélo, seulement si le passage est explicitement permis
+        Some(Barrier::Stile) if bicycle_allowed => 60.0,
+        Some(Barrier::Stile) => return None,
+    };
+    if node.bicycle == Some(Bicycle::Dismount) {
+        penalty += 30.0;
+    }
+    Some(penalty)
+}
+
+fn get_cost(
+    fast_or_safe: FastOrSafe,
+    weights: &CostWeights,
+    edge: &EdgePoint,
+    allow_ferry: bool,
+    departure: NaiveDate,
+) -> f64 {
     // if the target is the source we are reverse of the edge
     if SourceOrTarget::Source == edge.direction
         && (edge.oneway == Some(Oneway::Yes)
@@ -228,15 +703,19 @@ fn get_cost(fast_or_safe: FastOrSafe, edge: &EdgePoint, allow_ferry: bool) -> f6
             && edge.cycleway_right_oneway != Some(Oneway::No)
             || edge.cycleway_left == Some(Cycleway::Snow))
     {
-        return 10000.0;
+        return FORBIDDEN_COST;
     }
 
     if edge.winter_service_no && edge.snow {
-        return 10000.0;
+        return FORBIDDEN_COST;
     }
 
+    let Some(node_penalty) = get_node_penalty(edge) else {
+        return FORBIDDEN_COST;
+    };
+
     if edge.bicycle == Some(Bicycle::No) {
-        return 10000.0;
+        return FORBIDDEN_COST;
     }
 
     if edge.highway == Some(Highway::Proposed)
@@ -244,25 +723,25 @@ fn get_cost(fast_or_safe: FastOrSafe, edge: &EdgePoint, allow_ferry: bool) -> f6
         || edge.highway == Some(Highway::Motorway)
         || edge.highway == Some(Highway::Construction)
     {
-        return 10000.0;
+        return FORBIDDEN_COST;
     }
 
     if (edge.access == Some(Access::Private) || edge.access == Some(Access::No) || edge.informal)
         && edge.bicycle != Some(Bicycle::Yes)
     {
-        return 10000.0;
+        return FORBIDDEN_COST;
     }
 
     // Pénaliser fortement les traversiers si allow_ferry=false
     if !allow_ferry && edge.route == Some(Route::Ferry) {
-        return 10000.0;
+        return FORBIDDEN_COST;
     }
 
     let mut cost: f64 = if edge.highway == Some(Highway::Steps) {
         if edge.bicycle == Some(Bicycle::Yes) || edge.bicycle == Some(Bicycle::Designated) {
             15.0
         } else {
-            return 10000.0;
+            return FORBIDDEN_COST;
         }
     } else if edge.highway == Some(Highway::Path) {
         if edge.bicycle == Some(Bicycle::Yes) {
@@ -278,7 +757,7 @@ fn get_cost(fast_or_safe: FastOrSafe, edge: &EdgePoint, allow_ferry: bool) -> f6
         10.0
     } else if edge.routing_bicycle_use_sidepath {
         10.0
-    } else if let Some(cycleway_cost) = get_cycleway_cost(edge) {
+    } else if let Some(cycleway_cost) = get_cycleway_cost(edge, weights) {
         cycleway_cost
     } else if edge.highway == Some(Highway::Footway) || edge.highway == Some(Highway::Pedestrian) {
         if edge.bicycle == Some(Bicycle::Yes) || edge.bicycle == Some(Bicycle::Designated) {
@@ -304,12 +783,12 @@ fn get_cost(fast_or_safe: FastOrSafe, edge: &EdgePoint, allow_ferry: bool) -> f6
         } else {
             15.0
         }
-    } else if let Some(local_cost) = get_local_road_cost(edge) {
+    } else if let Some(local_cost) = get_local_road_cost(edge, weights) {
         local_cost
     } else if edge.route == Some(Route::Ferry) {
         10.
     } else if edge.highway == Some(Highway::Trunk) {
-        9.0
+        weights.trunk
     } else if edge.highway.is_some() {
         10.0
     } else {
@@ -320,32 +799,35 @@ fn get_cost(fast_or_safe: FastOrSafe, edge: &EdgePoint, allow_ferry: bool) -> f6
         cost = cost + 5.0;
     }
 
-    if edge.road_work {
+    if edge.road_work_on(departure) {
         cost *= 10.0;
     }
 
     cost = match fast_or_safe {
         FastOrSafe::Fast => {
-            cost = cost * elevation::get_edge_slope_cost(edge);
+            cost *= elevation::get_edge_slope_cost(edge).powf(weights.hills);
             1.0 + cost.log(20.0)
         }
         FastOrSafe::Safe => {
-            let slope_cost = elevation::get_edge_slope_cost(edge);
+            let slope_cost = elevation::get_edge_slope_cost(edge).powf(weights.hills);
             cost * slope_cost
         }
     };
 
-    if edge.reported {
-        cost * 10.0
-    } else {
-        cost
-    }
+    cost *= edge.report_penalty(departure);
+
+    // La pénalité du noeud est répartie sur l'edge, qui est multiplié par sa longueur
+    cost + node_penalty / edge.length.max(1.0)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::db::edge::{EdgePoint, SourceOrTarget};
-    use crate::utils::cost::{get_cost, FastOrSafe};
+    use crate::db::road_work::today;
+    use crate::utils::cost::{
+        get_cost, BikeType, CostProfile, CostWeights, FastOrSafe, Preferences, DEFAULT_WEIGHTS,
+        FORBIDDEN_COST,
+    };
 
     #[test]
     fn test_get_cost() {
@@ -353,7 +835,7 @@ mod tests {
             direction: SourceOrTarget::Source,
             ..EdgePoint::default()
         };
-        let cost = get_cost(FastOrSafe::Safe, &edge, true);
+        let cost = get_cost(FastOrSafe::Safe, &DEFAULT_WEIGHTS, &edge, true, today());
         assert_eq!(cost, 20.0);
     }
 
@@ -365,7 +847,210 @@ mod tests {
             surface: Some(crate::db::edge::Surface::Gravel),
             ..EdgePoint::default()
         };
-        let cost = get_cost(FastOrSafe::Safe, &edge, true);
+        let cost = get_cost(FastOrSafe::Safe, &DEFAULT_WEIGHTS, &edge, true, today());
         assert_eq!(cost, 1.1);
     }
+
+    #[test]
+    fn test_preferences() {
+        assert_eq!(
+            CostWeights::from_preferences(BikeType::Regular, &Preferences::default()),
+            DEFAULT_WEIGHTS
+        );
+        assert!(Preferences::new(Some(1.5), None, None, None).is_err());
+
+        let gravel = EdgePoint {
+            highway: Some(crate::db::edge::Highway::Cycleway),
+            surface: Some(crate::db::edge::Surface::Gravel),
+            ..EdgePoint::default()
+        };
+        let preferences = Preferences::new(None, Some(1.0), None, None).unwrap();
+        let profile = CostProfile::new("safe", BikeType::Regular, preferences).unwrap();
+        assert!(
+            profile.h().get_cost(&gravel, true)
+                > get_cost(FastOrSafe::Safe, &DEFAULT_WEIGHTS, &gravel, true, today())
+        );
+        assert!(CostProfile::new("scenic", BikeType::Regular, preferences).is_err());
+    }
+
+    #[test]
+    fn test_ebike() {
+        let climb = EdgePoint {
+            highway: Some(crate::db::edge::Highway::Residential),
+            length: 100.0,
+            elevation_start: Some(100),
+            elevation_end: Some(110),
+            ..EdgePoint::default()
+        };
+        let regular = CostProfile::new("safe", BikeType::Regular, Preferences::default()).unwrap();
+        let ebike = CostProfile::new("safe", BikeType::Ebike, Preferences::default()).unwrap();
+        assert!(ebike.h().get_cost(&climb, true) < regular.h().get_cost(&climb, true));
+
+        // Les interdictions restent les mêmes
+        let forbidden = EdgePoint {
+            bicycle: Some(crate::db::edge::Bicycle::No),
+            ..EdgePoint::default()
+        };
+        assert_eq!(ebike.h().get_cost(&forbidden, true), 10000.0);
+        assert!(BikeType::parse(Some("tandem")).is_err());
+    }
+
+    #[test]
+    fn test_road() {
+        let road = CostProfile::new("safe", BikeType::Road, Preferences::default()).unwrap();
+        let regular = CostProfile::new("safe", BikeType::Regular, Preferences::default()).unwrap();
+        let paved = EdgePoint {
+            highway: Some(crate::db::edge::Highway::Residential),
+            surface: Some(crate::db::edge::Surface::Asphalt),
+            ..EdgePoint::default()
+        };
+        assert_eq!(
+            road.h().get_cost(&paved, true),
+            regular.h().get_cost(&paved, true)
+        );
+
+        let dirt = EdgePoint {
+            surface: Some(crate::db::edge::Surface::Dirt),
+            ..paved.clone()
+        };
+        assert!(road.h().get_cost(&dirt, true) >= 10.0 * regular.h().get_cost(&dirt, true));
+
+        let very_bad = EdgePoint {
+            smoothness: Some(crate::db::edge::Smoothness::VeryBad),
+            ..paved
+        };
+        assert!(road.h().get_cost(&very_bad, true) > regular.h().get_cost(&very_bad, true));
+    }
+
+    #[test]
+    fn test_touring() {
+        let touring = CostProfile::new("safe", BikeType::Touring, Preferences::default()).unwrap();
+        let route_verte = EdgePoint {
+            highway: Some(crate::db::edge::Highway::Cycleway),
+            in_route_verte: true,
+            ..EdgePoint::default()
+        };
+        let other = EdgePoint {
+            in_route_verte: false,
+            ..route_verte.clone()
+        };
+        assert!(
+            touring.h().get_cost(&other, true) > 3.0 * touring.h().get_cost(&route_verte, true)
+        );
+    }
+
+    #[test]
+    fn test_cargo() {
+        let cargo = CostProfile::new("safe", BikeType::Cargo, Preferences::default()).unwrap();
+        let steps = EdgePoint {
+            highway: Some(crate::db::edge::Highway::Steps),
+            bicycle: Some(crate::db::edge::Bicycle::Yes),
+            ..EdgePoint::default()
+        };
+        assert_eq!(cargo.h().get_cost(&steps, true), FORBIDDEN_COST);
+
+        let narrow = EdgePoint {
+            highway: Some(crate::db::edge::Highway::Cycleway),
+            maxwidth: Some(0.9),
+            ..EdgePoint::default()
+        };
+        assert_eq!(cargo.h().get_cost(&narrow, true), FORBIDDEN_COST);
+
+        let wide = EdgePoint {
+            width: Some(3.0),
+            maxwidth: None,
+            ..narrow
+        };
+        assert_eq!(cargo.h().get_cost(&wide, true), 1.0);
+    }
+
+    #[test]
+    fn test_node_barrier() {
+        use crate::db::edge::{Barrier, NodeAttributes};
+        let safe = CostProfile::new("safe", BikeType::Regular, Preferences::default()).unwrap();
+        let cargo = CostProfile::new("safe", BikeType::Cargo, Preferences::default()).unwrap();
+        let cycleway = |node: NodeAttributes| EdgePoint {
+            highway: Some(crate::db::edge::Highway::Cycleway),
+            length: 10.0,
+            target_node: Some(node),
+            ..EdgePoint::default()
+        };
+
+        // 5 m de pénalité répartis sur 10 m
+        let bollard = cycleway(NodeAttributes {
+            barrier: Some(Barrier::Bollard),
+            ..NodeAttributes::default()
+        });
+        assert_eq!(safe.h().get_cost(&bollard, true), 1.5);
+        // La pénalité ne s'applique qu'en arrivant au noeud
+        assert_eq!(safe.h().get_cost(&bollard.reverse(), true), 1.0);
+
+        let locked = cycleway(NodeAttributes {
+            barrier: Some(Barrier::Gate),
+            locked: true,
+            ..NodeAttributes::default()
+        });
+        assert_eq!(safe.h().get_cost(&locked, true), FORBIDDEN_COST);
+
+        let cycle_barrier = cycleway(NodeAttributes {
+            barrier: Some(Barrier::Cycle),
+            ..NodeAttributes::default()
+        });
+        assert_eq!(safe.h().get_cost(&cycle_barrier, true), 4.0);
+        assert_eq!(cargo.h().get_cost(&cycle_barrier, true), FORBIDDEN_COST);
+
+        // Traversée sans barrière: ses tags d'accès ne bloquent pas la rue qui la porte
+        let crossing = cycleway(NodeAttributes {
+            bicycle: Some(crate::db::edge::Bicycle::No),
+            crossing: Some(crate::db::edge::Crossing::Marked),
+            ..NodeAttributes::default()
+        });
+        assert_eq!(safe.h().get_cost(&crossing, true), 1.0);
+    }
+
+    #[test]
+    fn test_avoid() {
+        let areas = crate::utils::avoid::AvoidAreas::parse(
+            r#"{"type": "Polygon", "coordinates": [[[-73.6, 45.5], [-73.5, 45.5], [-73.5, 45.6], [-73.6, 45.6], [-73.6, 45.5]]]}"#,
+        )
+        .unwrap();
+        let profile = CostProfile::new("safe", BikeType::Regular, Preferences::default())
+            .unwrap()
+            .with_avoid(Some(std::sync::Arc::new(areas)));
+        let inside = EdgePoint {
+            lon1: -73.58,
+            lat1: 45.52,
+            lon2: -73.57,
+            lat2: 45.53,
+            ..EdgePoint::default()
+        };
+        assert_eq!(profile.h().get_cost(&inside, true), FORBIDDEN_COST);
+        let outside = EdgePoint {
+            lon1: -73.7,
+            lat1: 45.4,
+            lon2: -73.65,
+            lat2: 45.45,
+            ..inside
+        };
+        assert_eq!(profile.h().get_cost(&outside, true), 20.0);
+    }
+
+    #[test]
+    fn test_road_work() {
+        let date = |day: u32| chrono::NaiveDate::from_ymd_opt(2025, 6, day).unwrap();
+        let edge = EdgePoint {
+            road_work: vec![crate::db::road_work::RoadworkPeriod {
+                start_date: Some(date(10)),
+                end_date: Some(date(20)),
+            }],
+            ..EdgePoint::default()
+        };
+        let profile = |day: u32| {
+            CostProfile::new("safe", BikeType::Regular, Preferences::default())
+                .unwrap()
+                .with_departure(date(day))
+        };
+        assert_eq!(profile(15).h().get_cost(&edge, true), 200.0);
+        assert_eq!(profile(21).h().get_cost(&edge, true), 20.0);
+    }
 }
@@ -1,6 +1,14 @@
 /// Elevation utilities for SRTM integration
 /// Calculates slope-based cost multipliers for the routing algorithm
-use crate::db::edge::EdgePoint;
+use serde::Serialize;
+
+use crate::db::edge::{EdgePoint, Point, SourceOrTarget};
+use crate::db::utils::distance_meters;
+
+/// Distance minimale (m) sur laquelle une pente doit se maintenir pour compter comme pente soutenue
+const SUSTAINED_GRADE_DISTANCE: f64 = 200.0;
+/// Variation (m) en dessous de laquelle on considère que c'est du bruit SRTM
+const ELEVATION_NOISE: f64 = 2.0;
 
 /// Calculate the slope percentage between two elevations over a distance
 pub fn calculate_slope_percentage(
@@ -24,6 +32,79 @@ pub fn get_edge_slope_cost(edge: &EdgePoint) -> f64 {
     }
 }
 
+/// Profil d'élévation d'un itinéraire
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ElevationProfile {
+    /// Paires (distance cumulée en mètres, altitude en mètres)
+    pub profile: Vec<(f64, f64)>,
+    /// Dénivelé positif total en mètres
+    pub ascent: f64,
+    /// Dénivelé négatif total en mètres
+    pub descent: f64,
+    /// Pente maximale (%) maintenue sur au moins 200 m
+    pub max_sustained_grade: f64,
+}
+
+/// Altitude du noeud d'arrivée d'un segment
+fn arrival_elevation(edge: &EdgePoint) -> Option<i16> {
+    match edge.direction {
+        SourceOrTarget::Source => edge.elevation_start,
+        SourceOrTarget::Target => edge.elevation_end,
+    }
+}
+
+pub fn get_elevation_profile(points: &[Point]) -> ElevationProfile {
+    let mut profile = vec![];
+    let mut distance = 0.0;
+    for (i, point) in points.iter().enumerate() {
+        if i > 0 {
+            let previous = &points[i - 1];
+            distance += distance_meters(previous.lat, previous.lng, point.lat, point.lng);
+        }
+        if let Some(elevation) = point.edge.as_deref().and_then(arrival_elevation) {
+            profile.push((distance, elevation as f64));
+        }
+    }
+
+    // Dénivelés avec hystérésis pour ne pas additionner le bruit des données SRTM
+    let mut ascent = 0.0;
+    let mut descent = 0.0;
+    if let Some((_, first)) = profile.first() {
+        let mut reference = *first;
+        for (_, elevation) in profile.iter().skip(1) {
+            let diff = elevation - reference;
+            if diff >= ELEVATION_NOISE {
+                ascent += diff;
+                reference = *elevation;
+            } else if diff <= -ELEVATION_NOISE {
+                descent -= diff;
+                reference = *elevation;
+            }
+        }
+    }
+
+    let mut max_sustained_grade: f64 = 0.0;
+    let mut end = 0;
+    for start in 0..profile.len() {
+        while end < profile.len() && profile[end].0 - profile[start].0 < SUSTAINED_GRADE_DISTANCE {
+            end += 1;
+        }
+        if end == profile.len() {
+            break;
+        }
+        let grade =
+            (profile[end].1 - profile[start].1) / (profile[end].0 - profile[start].0) * 100.0;
+        max_sustained_grade = max_sustained_grade.max(grade);
+    }
+
+    ElevationProfile {
+        profile,
+        ascent,
+        descent,
+        max_sustained_grade,
+    }
+}
+
 fn sigmoid_transition(x: f64) -> f64 {
     // --- 1. VOS PARAMÈTRES RÉGLABLES ---
     let steepness: f64 = 0.6;
@@ -64,6 +145,35 @@ mod tests {
         assert!((calculate_slope_percentage(100, 100, 100.0) - 0.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_elevation_profile() {
+        // 10 segments de ~111 m vers le nord: montée de 30 m puis descente de 10 m
+        let elevations = [100, 105, 110, 115, 120, 125, 130, 128, 126, 122, 120];
+        let points: Vec<Point> = elevations
+            .iter()
+            .enumerate()
+            .map(|(i, elevation)| Point {
+                lng: -73.0,
+                lat: 45.0 + i as f64 * 0.001,
+                way_id: 0,
+                node_id: 0,
+                length: 0.0,
+                ferry: false,
+                name: None,
+                edge: Some(std::sync::Arc::new(EdgePoint {
+                    elevation_end: Some(*elevation),
+                    ..Default::default()
+                })),
+            })
+            .collect();
+        let profile = get_elevation_profile(&points);
+        assert_eq!(profile.profile.len(), 11);
+        assert_eq!(profile.ascent, 30.0);
+        assert_eq!(profile.descent, 10.0);
+        // 10 m sur ~222 m
+        assert!((profile.max_sustained_grade - 4.5).abs() < 0.1);
+    }
+
     #[test]
     fn test_slope_cost() {
         // Test with no elevation data
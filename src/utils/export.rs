@@ -0,0 +1,145 @@
+//! Export des itinéraires calculés vers des formats lisibles par les GPS et autres applications
+//! (GPX 1.1 pour Garmin/Wahoo, GeoJSON pour les outils web)
+
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject, Value};
+use serde_json::json;
+
+use crate::db::edge::Point;
+
+/// Points où le nom de la rue change, utilisés comme points de route nommés.
+fn named_points(points: &[Point]) -> Vec<&Point> {
+    let mut named = vec![];
+    let mut last_name: Option<&String> = None;
+    for point in points {
+        if let Some(name) = &point.name {
+            if last_name != Some(name) {
+                named.push(point);
+                last_name = Some(name);
+            }
+        }
+    }
+    named
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Produit un document GPX 1.1 contenant la trace complète et les points de route nommés.
+pub fn to_gpx(points: &[Point], name: &str) -> String {
+    let name = escape_xml(name);
+    let mut gpx = String::new();
+    gpx.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    gpx.push('\n');
+    gpx.push_str(
+        r#"<gpx version="1.1" creator="VéloInfo" xmlns="http://www.topografix.com/GPX/1/1">"#,
+    );
+    gpx.push('\n');
+    gpx.push_str(&format!("  <metadata><name>{}</name></metadata>\n", name));
+
+    gpx.push_str(&format!("  <rte>\n    <name>{}</name>\n", name));
+    for point in named_points(points) {
+        gpx.push_str(&format!(
+            "    <rtept lat=\"{}\" lon=\"{}\"><name>{}</name></rtept>\n",
+            point.lat,
+            point.lng,
+            escape_xml(point.name.as_deref().unwrap_or_default())
+        ));
+    }
+    gpx.push_str("  </rte>\n");
+
+    gpx.push_str(&format!(
+        "  <trk>\n    <name>{}</name>\n    <trkseg>\n",
+        name
+    ));
+    for point in points {
+        gpx.push_str(&format!(
+            "      <trkpt lat=\"{}\" lon=\"{}\"></trkpt>\n",
+            point.lat, point.lng
+        ));
+    }
+    gpx.push_str("    </trkseg>\n  </trk>\n</gpx>\n");
+    gpx
+}
+
+/// Produit une FeatureCollection avec la trace (LineString) et les points de route nommés.
+pub fn to_geojson(points: &[Point], name: &str) -> FeatureCollection {
+    let mut properties = JsonObject::new();
+    properties.insert("name".to_string(), json!(name));
+    let line = Feature {
+        bbox: None,
+        geometry: Some(Geometry::new(Value::LineString(
+            points
+                .iter()
+                .map(|point| vec![point.lng, point.lat])
+                .collect(),
+        ))),
+        id: None,
+        properties: Some(properties),
+        foreign_members: None,
+    };
+
+    let waypoints = named_points(points).into_iter().map(|point| {
+        let mut properties = JsonObject::new();
+        properties.insert("name".to_string(), json!(point.name));
+        Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::Point(vec![point.lng, point.lat]))),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        }
+    });
+
+    FeatureCollection {
+        bbox: None,
+        features: std::iter::once(line).chain(waypoints).collect(),
+        foreign_members: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(lng: f64, lat: f64, name: Option<&str>) -> Point {
+        Point {
+            lng,
+            lat,
+            way_id: 0,
+            node_id: 0,
+            length: 0.0,
+            ferry: false,
+            name: name.map(|n| n.to_string()),
+            edge: None,
+        }
+    }
+
+    #[test]
+    fn test_gpx() {
+        let points = vec![
+            point(-73.5, 45.5, None),
+            point(-73.51, 45.51, Some("Rue Saint-Denis")),
+            point(-73.52, 45.52, Some("Rue Saint-Denis")),
+            point(-73.53, 45.53, Some("Boul. <Rosemont> & cie")),
+        ];
+        let gpx = to_gpx(&points, "safe");
+        assert_eq!(gpx.matches("<trkpt").count(), 4);
+        assert_eq!(gpx.matches("<rtept").count(), 2);
+        assert!(gpx.contains("Boul. &lt;Rosemont&gt; &amp; cie"));
+    }
+
+    #[test]
+    fn test_geojson() {
+        let points = vec![
+            point(-73.5, 45.5, Some("Rue Rachel")),
+            point(-73.51, 45.51, Some("Rue Rachel")),
+        ];
+        let collection = to_geojson(&points, "fast");
+        assert_eq!(collection.features.len(), 2);
+    }
+}
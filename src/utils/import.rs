@@ -1,4 +1,5 @@
 use crate::db::edge::Edge;
+use crate::db::snapshot::{self, SNAPSHOT_PATH};
 use sqlx::PgPool;
 use tokio::process::Command;
 
@@ -18,6 +19,10 @@ pub async fn import(conn: &PgPool) {
             println!("Error2 importing: {:?}", e);
         }
     }
+    println!("writing routing graph snapshot");
+    if let Err(e) = snapshot::write_from_db(conn, SNAPSHOT_PATH).await {
+        println!("Error writing routing graph snapshot: {}", e);
+    }
     println!("clearing cache");
     Edge::clear_cache_and_reload(&conn).await;
 }
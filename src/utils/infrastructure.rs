@@ -0,0 +1,126 @@
+//! Répartition d'un itinéraire par type d'infrastructure, pour comparer les options
+//! proposées. La classification est celle de `cost::get_infrastructure`.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::db::edge::Point;
+use crate::db::utils::distance_meters;
+use crate::utils::cost::{get_infrastructure, Infrastructure};
+
+/// Distances en mètres. Les travaux et signalements recoupent les autres catégories.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct InfrastructureBreakdown {
+    pub track: f64,
+    pub lane: f64,
+    pub shared_lane: f64,
+    pub residential: f64,
+    pub arterial: f64,
+    pub ferry: f64,
+    pub other: f64,
+    /// Edges touchés par une entrave en vigueur le jour du départ
+    pub road_work: f64,
+    pub reported: f64,
+}
+
+impl InfrastructureBreakdown {
+    /// Les points ajoutés à la main (départ, arrivée) ne sont pas comptés
+    pub fn from_points(points: &[Point], departure: NaiveDate) -> InfrastructureBreakdown {
+        let mut breakdown = InfrastructureBreakdown::default();
+        for (previous, point) in points.iter().zip(points.iter().skip(1)) {
+            let Some(edge) = &point.edge else {
+                continue;
+            };
+            let distance = distance_meters(previous.lat, previous.lng, point.lat, point.lng);
+            *match get_infrastructure(edge) {
+                Infrastructure::Track => &mut breakdown.track,
+                Infrastructure::Lane => &mut breakdown.lane,
+                Infrastructure::SharedLane => &mut breakdown.shared_lane,
+                Infrastructure::Residential => &mut breakdown.residential,
+                Infrastructure::Arterial => &mut breakdown.arterial,
+                Infrastructure::Ferry => &mut breakdown.ferry,
+                Infrastructure::Other => &mut breakdown.other,
+            } += distance;
+            if edge.road_work_on(departure) {
+                breakdown.road_work += distance;
+            }
+            if !edge.reports.is_empty() {
+                breakdown.reported += distance;
+            }
+        }
+        breakdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::{Cycleway, EdgePoint, Highway, Route, SourceOrTarget};
+    use crate::db::report::EdgeReport;
+    use crate::db::road_work::{today, RoadworkPeriod};
+    use std::sync::Arc;
+
+    fn point(lng: f64, edge: Option<EdgePoint>) -> Point {
+        Point {
+            lng,
+            lat: 45.5,
+            way_id: 0,
+            node_id: 0,
+            length: 0.0,
+            ferry: false,
+            name: None,
+            edge: edge.map(Arc::new),
+        }
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let highway = |highway: Highway| EdgePoint {
+            highway: Some(highway),
+            ..EdgePoint::default()
+        };
+        // La bande de droite ne sert que dans le sens de l'edge
+        let lane = |direction: SourceOrTarget| EdgePoint {
+            cycleway_right: Some(Cycleway::Lane),
+            direction,
+            ..highway(Highway::Secondary)
+        };
+        let road_work = EdgePoint {
+            road_work: vec![RoadworkPeriod {
+                start_date: None,
+                end_date: None,
+            }],
+            reports: vec![EdgeReport {
+                score: 0.5,
+                date: today(),
+            }],
+            ..highway(Highway::Residential)
+        };
+        let ferry = EdgePoint {
+            route: Some(Route::Ferry),
+            ..EdgePoint::default()
+        };
+        let points = [
+            point(-73.6, None),
+            point(-73.59, Some(highway(Highway::Cycleway))),
+            point(-73.58, Some(lane(SourceOrTarget::Target))),
+            point(-73.57, Some(lane(SourceOrTarget::Source))),
+            point(-73.56, Some(road_work)),
+            point(-73.55, Some(ferry)),
+            point(-73.54, Some(highway(Highway::Footway))),
+            point(-73.53, None),
+        ];
+        let breakdown = InfrastructureBreakdown::from_points(&points, today());
+        let segment = breakdown.track;
+        assert!(segment > 700.0);
+        let close = |distance: f64| (distance - segment).abs() < 1.0;
+        assert!(close(breakdown.lane));
+        assert!(close(breakdown.arterial));
+        assert!(close(breakdown.residential));
+        assert!(close(breakdown.road_work));
+        assert!(close(breakdown.reported));
+        assert!(close(breakdown.ferry));
+        assert!(close(breakdown.other));
+        assert_eq!(breakdown.shared_lane, 0.0);
+    }
+}
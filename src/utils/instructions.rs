@@ -0,0 +1,265 @@
+//! Génération des instructions de navigation (tourner à gauche/droite, continuer, carrefour
+//! giratoire, traversier) à partir de la suite de points retournée par `a_star_bidirectional`.
+
+use serde::Serialize;
+
+use crate::db::edge::Point;
+use crate::db::utils::{bearing, distance_meters};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Maneuver {
+    Depart,
+    Continue,
+    SlightLeft,
+    Left,
+    SharpLeft,
+    SlightRight,
+    Right,
+    SharpRight,
+    UTurn,
+    Roundabout,
+    Ferry,
+    Arrive,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Instruction {
+    pub maneuver: Maneuver,
+    /// Nom de la rue empruntée après la manoeuvre
+    pub name: Option<String>,
+    /// Index du point de l'itinéraire où se fait la manoeuvre
+    pub index: usize,
+    pub lng: f64,
+    pub lat: f64,
+    /// Distance en mètres jusqu'à la prochaine manoeuvre
+    pub distance: f64,
+}
+
+/// Classe le changement de cap (en degrés, positif = vers la droite)
+fn maneuver_from_angle(angle: f64) -> Maneuver {
+    let abs = angle.abs();
+    let right = angle > 0.0;
+    if abs < 20.0 {
+        Maneuver::Continue
+    } else if abs < 45.0 {
+        if right {
+            Maneuver::SlightRight
+        } else {
+            Maneuver::SlightLeft
+        }
+    } else if abs < 120.0 {
+        if right {
+            Maneuver::Right
+        } else {
+            Maneuver::Left
+        }
+    } else if abs < 170.0 {
+        if right {
+            Maneuver::SharpRight
+        } else {
+            Maneuver::SharpLeft
+        }
+    } else {
+        Maneuver::UTurn
+    }
+}
+
+/// Différence de cap entre -180 et 180 degrés
+fn turn_angle(bearing_in: f64, bearing_out: f64) -> f64 {
+    (bearing_out - bearing_in + 540.0) % 360.0 - 180.0
+}
+
+fn is_roundabout(point: &Point) -> bool {
+    point.edge.as_ref().is_some_and(|edge| edge.roundabout)
+}
+
+/// Cap du segment arrivant au point `i`, en ignorant les points confondus
+fn bearing_in(points: &[Point], i: usize) -> Option<f64> {
+    let to = &points[i];
+    points[..i]
+        .iter()
+        .rev()
+        .find(|from| from.lng != to.lng || from.lat != to.lat)
+        .map(|from| bearing(from.lat, from.lng, to.lat, to.lng))
+}
+
+/// Cap du segment partant du point `i`, en ignorant les points confondus
+fn bearing_out(points: &[Point], i: usize) -> Option<f64> {
+    let from = &points[i];
+    points[i + 1..]
+        .iter()
+        .find(|to| to.lng != from.lng || to.lat != from.lat)
+        .map(|to| bearing(from.lat, from.lng, to.lat, to.lng))
+}
+
+pub fn get_instructions(points: &[Point]) -> Vec<Instruction> {
+    if points.len() < 2 {
+        return vec![];
+    }
+
+    let instruction = |maneuver: Maneuver, index: usize, name: Option<String>| Instruction {
+        maneuver,
+        name,
+        index,
+        lng: points[index].lng,
+        lat: points[index].lat,
+        distance: 0.0,
+    };
+
+    let first_name = points.iter().find_map(|point| point.name.clone());
+    let mut instructions = vec![instruction(Maneuver::Depart, 0, first_name)];
+    let mut current_name = points[1].name.clone();
+
+    let mut i = 1;
+    while i < points.len() - 1 {
+        // Le segment i -> i+1 appartient à l'edge du point i+1
+        let incoming = &points[i];
+        let outgoing = &points[i + 1];
+
+        if outgoing.ferry && !incoming.ferry {
+            instructions.push(instruction(Maneuver::Ferry, i, outgoing.name.clone()));
+            current_name = outgoing.name.clone();
+            i += 1;
+            continue;
+        }
+
+        if is_roundabout(outgoing) && !is_roundabout(incoming) {
+            // On avance jusqu'à la sortie du carrefour giratoire pour connaître la rue prise
+            let entry = i;
+            let mut exit = i + 1;
+            while exit < points.len() - 1 && is_roundabout(&points[exit + 1]) {
+                exit += 1;
+            }
+            let exit_name = points
+                .get(exit + 1)
+                .and_then(|point| point.name.clone())
+                .or_else(|| current_name.clone());
+            instructions.push(instruction(Maneuver::Roundabout, entry, exit_name.clone()));
+            current_name = exit_name;
+            i = exit + 1;
+            continue;
+        }
+
+        let (Some(bearing_in), Some(bearing_out)) = (bearing_in(points, i), bearing_out(points, i))
+        else {
+            i += 1;
+            continue;
+        };
+        let maneuver = maneuver_from_angle(turn_angle(bearing_in, bearing_out));
+        let name_changed = outgoing.name.is_some() && outgoing.name != current_name;
+        let way_changed = outgoing.way_id != incoming.way_id;
+
+        // Une rue courbe ne génère pas d'instruction tant qu'on reste sur la même rue
+        let is_turn = matches!(
+            maneuver,
+            Maneuver::Left
+                | Maneuver::Right
+                | Maneuver::SharpLeft
+                | Maneuver::SharpRight
+                | Maneuver::UTurn
+        );
+        if name_changed || (way_changed && is_turn) {
+            instructions.push(instruction(maneuver, i, outgoing.name.clone()));
+        }
+        if outgoing.name.is_some() {
+            current_name = outgoing.name.clone();
+        }
+        i += 1;
+    }
+
+    instructions.push(instruction(Maneuver::Arrive, points.len() - 1, None));
+
+    // Distance jusqu'à la prochaine manoeuvre
+    let next_indexes: Vec<usize> = instructions.iter().skip(1).map(|i| i.index).collect();
+    for (instruction, next_index) in instructions.iter_mut().zip(next_indexes) {
+        instruction.distance = points[instruction.index..=next_index]
+            .windows(2)
+            .map(|w| distance_meters(w[0].lat, w[0].lng, w[1].lat, w[1].lng))
+            .sum();
+    }
+
+    instructions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::EdgePoint;
+    use std::sync::Arc;
+
+    fn point(lng: f64, lat: f64, way_id: i64, name: &str) -> Point {
+        Point {
+            lng,
+            lat,
+            way_id,
+            node_id: 0,
+            length: 0.0,
+            ferry: false,
+            name: Some(name.to_string()),
+            edge: None,
+        }
+    }
+
+    #[test]
+    fn test_turn_angle() {
+        assert_eq!(turn_angle(0.0, 90.0), 90.0);
+        assert_eq!(turn_angle(350.0, 10.0), 20.0);
+        assert_eq!(turn_angle(10.0, 280.0), -90.0);
+    }
+
+    #[test]
+    fn test_right_turn() {
+        // Vers le nord sur la rue A puis vers l'est sur la rue B
+        let points = vec![
+            point(-73.0, 45.0, 1, "A"),
+            point(-73.0, 45.001, 1, "A"),
+            point(-73.0, 45.002, 1, "A"),
+            point(-72.999, 45.002, 2, "B"),
+            point(-72.998, 45.002, 2, "B"),
+        ];
+        let instructions = get_instructions(&points);
+        let maneuvers: Vec<Maneuver> = instructions.iter().map(|i| i.maneuver).collect();
+        assert_eq!(
+            maneuvers,
+            vec![Maneuver::Depart, Maneuver::Right, Maneuver::Arrive]
+        );
+        assert_eq!(instructions[1].name, Some("B".to_string()));
+        assert_eq!(instructions[1].index, 2);
+        assert!((instructions[0].distance - 222.4).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_roundabout_and_ferry() {
+        let roundabout = Some(Arc::new(EdgePoint {
+            roundabout: true,
+            ..EdgePoint::default()
+        }));
+        let mut points = vec![
+            point(-73.0, 45.0, 1, "A"),
+            point(-73.0, 45.001, 1, "A"),
+            point(-72.9995, 45.0015, 2, "Rond-point"),
+            point(-73.0, 45.002, 2, "Rond-point"),
+            point(-73.0, 45.003, 3, "C"),
+            point(-73.0, 45.004, 4, "Traverse"),
+            point(-73.0, 45.005, 5, "D"),
+        ];
+        points[2].edge = roundabout.clone();
+        points[3].edge = roundabout;
+        points[5].ferry = true;
+
+        let instructions = get_instructions(&points);
+        let maneuvers: Vec<Maneuver> = instructions.iter().map(|i| i.maneuver).collect();
+        assert_eq!(
+            maneuvers,
+            vec![
+                Maneuver::Depart,
+                Maneuver::Roundabout,
+                Maneuver::Ferry,
+                Maneuver::Continue,
+                Maneuver::Arrive
+            ]
+        );
+        assert_eq!(instructions[1].name, Some("C".to_string()));
+    }
+}
@@ -0,0 +1,251 @@
+//! Isochrones: zone atteignable à vélo en N minutes depuis un point.
+//! Un Dijkstra borné parcourt le réseau avec les fonctions de coût `H` (les chemins suivis
+//! sont donc ceux que l'itinéraire choisirait) et accumule le temps avec le `SpeedModel`.
+//! Chaque seuil produit une bande (polygone concave moins la bande précédente).
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc as ARc;
+
+use geo::{BooleanOps, ConcaveHull, Coord, MultiPoint, MultiPolygon, Point as GeoPoint};
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject, Value};
+use serde_json::json;
+use sqlx::Postgres;
+
+use crate::db::edge::{Edge, EdgePoint, SourceOrTarget};
+use crate::db::graph::GraphView;
+use crate::db::utils::Score;
+use crate::utils::cost::{FORBIDDEN_COST, H};
+use crate::utils::speed::SpeedModel;
+
+/// Seuils par défaut en minutes
+pub const DEFAULT_MINUTES: [f64; 3] = [10.0, 20.0, 30.0];
+pub const MAX_MINUTES: f64 = 60.0;
+const MAX_THRESHOLDS: usize = 6;
+/// Plus la valeur est grande, plus le polygone est convexe
+const CONCAVITY: f64 = 2.0;
+
+/// Portion de réseau parcourue: d'un noeud atteint au temps `start_time`
+/// jusqu'au noeud suivant atteint au temps `end_time` (secondes).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReachedSegment {
+    pub from: (f64, f64),
+    pub to: (f64, f64),
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+impl ReachedSegment {
+    /// Point atteint sur le segment au temps `time`
+    fn point_at(&self, time: f64) -> (f64, f64) {
+        if time >= self.end_time {
+            return self.to;
+        }
+        let duration = self.end_time - self.start_time;
+        let fraction = if duration > 0.0 {
+            ((time - self.start_time) / duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        (
+            self.from.0 + (self.to.0 - self.from.0) * fraction,
+            self.from.1 + (self.to.1 - self.from.1) * fraction,
+        )
+    }
+}
+
+/// Lit une liste de seuils en minutes au format `10,20,30`, triée en ordre croissant
+pub fn parse_minutes(minutes: &str) -> Result<Vec<f64>, String> {
+    let mut thresholds = minutes
+        .split(',')
+        .map(|m| {
+            m.trim()
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid minutes: {}", m))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    if thresholds.is_empty() || thresholds.len() > MAX_THRESHOLDS {
+        return Err(format!(
+            "Expected between 1 and {} thresholds, got {}",
+            MAX_THRESHOLDS,
+            thresholds.len()
+        ));
+    }
+    if let Some(m) = thresholds
+        .iter()
+        .find(|m| !(**m > 0.0 && **m <= MAX_MINUTES))
+    {
+        return Err(format!(
+            "Invalid minutes {}: must be between 0 and {}",
+            m, MAX_MINUTES
+        ));
+    }
+    thresholds.sort_by(f64::total_cmp);
+    thresholds.dedup();
+    Ok(thresholds)
+}
+
+/// Coordonnées (lng, lat) du noeud de départ et d'arrivée d'un edge selon le sens de parcours
+fn edge_ends(edge: &EdgePoint) -> ((f64, f64), (f64, f64)) {
+    match edge.direction {
+        SourceOrTarget::Target => ((edge.lon1, edge.lat1), (edge.lon2, edge.lat2)),
+        SourceOrTarget::Source => ((edge.lon2, edge.lat2), (edge.lon1, edge.lat1)),
+    }
+}
+
+/// Dijkstra borné: retourne toutes les portions de réseau atteintes en moins de `max_time` secondes.
+pub async fn reachable_segments(
+    start_node_id: i64,
+    max_time: f64,
+    h: Box<dyn H>,
+    speed_model: &SpeedModel,
+    allow_ferry: bool,
+    conn: &sqlx::Pool<Postgres>,
+) -> Result<Vec<ReachedSegment>, String> {
+    let start = Edge::get(start_node_id, conn)
+        .await
+        .map_err(|e| format!("Error while fetching node {}: {}", start_node_id, e))?;
+    let graph = GraphView::current();
+
+    // Les états sont conservés dans un Vec; la file de priorité contient leur index
+    let mut states: Vec<(ARc<EdgePoint>, f64)> = vec![(start.clone(), 0.0)];
+    let mut g_score: HashMap<ARc<EdgePoint>, f64> = HashMap::new();
+    g_score.insert(start, 0.0);
+    let mut open_set = BinaryHeap::new();
+    open_set.push(Reverse((Score(0.0), 0)));
+
+    // Une seule portion par edge: celle atteinte le plus tôt
+    let mut segments: HashMap<ARc<EdgePoint>, ReachedSegment> = HashMap::new();
+    while let Some(Reverse((Score(cost), index))) = open_set.pop() {
+        let (current, time) = states[index].clone();
+        if cost > *g_score.get(&current).unwrap_or(&f64::INFINITY) {
+            continue;
+        }
+        for neighbor in current.get_neighbors(graph.as_ref(), conn).await.iter() {
+            let edge_cost = h.get_cost(&neighbor, allow_ferry);
+            if edge_cost >= FORBIDDEN_COST {
+                continue;
+            }
+            let end_time = time + speed_model.edge_duration(&neighbor, neighbor.length);
+            if segments
+                .get(&neighbor)
+                .is_none_or(|segment| time < segment.start_time)
+            {
+                let (from, to) = edge_ends(&neighbor);
+                segments.insert(
+                    neighbor.clone(),
+                    ReachedSegment {
+                        from,
+                        to,
+                        start_time: time,
+                        end_time,
+                    },
+                );
+            }
+            if end_time > max_time {
+                continue;
+            }
+            let tentative_g_score = cost + neighbor.length * edge_cost;
+            if tentative_g_score < *g_score.get(&neighbor).unwrap_or(&f64::INFINITY) {
+                g_score.insert(neighbor.clone(), tentative_g_score);
+                states.push((neighbor.clone(), end_time));
+                open_set.push(Reverse((Score(tentative_g_score), states.len() - 1)));
+            }
+        }
+    }
+    Ok(segments.into_values().collect())
+}
+
+/// Polygone concave des points atteints en moins de `time` secondes
+fn reachable_area(segments: &[ReachedSegment], time: f64) -> Option<MultiPolygon> {
+    let points: Vec<GeoPoint> = segments
+        .iter()
+        .filter(|segment| segment.start_time <= time)
+        .flat_map(|segment| [segment.from, segment.point_at(time)])
+        .map(|(lng, lat)| GeoPoint::from(Coord { x: lng, y: lat }))
+        .collect();
+    if points.len() < 3 {
+        return None;
+    }
+    let hull = MultiPoint::new(points).concave_hull(CONCAVITY);
+    Some(MultiPolygon::new(vec![hull]))
+}
+
+/// Une bande GeoJSON par seuil (en minutes, triés en ordre croissant).
+/// Chaque bande exclut la zone déjà couverte par le seuil précédent.
+pub fn isochrone_bands(segments: &[ReachedSegment], minutes: &[f64]) -> FeatureCollection {
+    let mut features = vec![];
+    let mut previous: Option<MultiPolygon> = None;
+    for minute in minutes {
+        let Some(area) = reachable_area(segments, minute * 60.0) else {
+            continue;
+        };
+        let band = match &previous {
+            Some(previous) => area.difference(previous),
+            None => area.clone(),
+        };
+        previous = Some(area);
+
+        let mut properties = JsonObject::new();
+        properties.insert("minutes".to_string(), json!(minute));
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::from(&band))),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        });
+    }
+    FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_minutes() {
+        assert_eq!(parse_minutes("20,10,30").unwrap(), vec![10.0, 20.0, 30.0]);
+        assert!(parse_minutes("10,abc").is_err());
+        assert!(parse_minutes("0").is_err());
+        assert!(parse_minutes("90").is_err());
+    }
+
+    #[test]
+    fn test_isochrone_bands() {
+        // Étoile de segments partant de l'origine, 5 minutes par segment
+        let segments: Vec<ReachedSegment> = [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)]
+            .iter()
+            .flat_map(|(x, y)| {
+                [
+                    ReachedSegment {
+                        from: (0.0, 0.0),
+                        to: (x * 0.01, y * 0.01),
+                        start_time: 0.0,
+                        end_time: 300.0,
+                    },
+                    ReachedSegment {
+                        from: (x * 0.01, y * 0.01),
+                        to: (x * 0.02, y * 0.02),
+                        start_time: 300.0,
+                        end_time: 600.0,
+                    },
+                ]
+            })
+            .collect();
+        assert_eq!(segments[1].point_at(450.0), (0.015, 0.0));
+
+        let bands = isochrone_bands(&segments, &[5.0, 10.0]);
+        assert_eq!(bands.features.len(), 2);
+        let minutes: Vec<f64> = bands
+            .features
+            .iter()
+            .map(|feature| feature.property("minutes").unwrap().as_f64().unwrap())
+            .collect();
+        assert_eq!(minutes, vec![5.0, 10.0]);
+    }
+}
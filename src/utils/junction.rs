@@ -0,0 +1,124 @@
+//! Coût des intersections, en mètres équivalents, ajouté par `H::turn_cost` entre l'arc
+//! qui arrive à un noeud et celui qui en repart: virages, virages à gauche à travers
+//! la circulation et traversées d'artères sans feux.
+
+use crate::db::edge::{Crossing, EdgePoint, Highway};
+
+/// Virage de plus de 45°
+const TURN_PENALTY: f64 = 10.0;
+/// Demi-tour
+const U_TURN_PENALTY: f64 = 60.0;
+/// Virage à gauche, selon la classe de la route la plus importante de l'intersection
+const LEFT_TURN_PENALTIES: [f64; 5] = [0.0, 30.0, 60.0, 100.0, 200.0];
+/// Traversée d'une artère qu'on n'emprunte pas, selon sa classe
+const CROSSING_PENALTIES: [f64; 5] = [0.0, 0.0, 60.0, 120.0, 300.0];
+/// Part des pénalités conservée à une traversée avec feux ou marquée
+const SIGNALS_FACTOR: f64 = 0.25;
+const MARKED_FACTOR: f64 = 0.6;
+
+/// Classe d'artère: 0 pour une rue locale ou un aménagement cyclable, 4 pour une autoroute
+pub fn arterial_rank(highway: Option<&Highway>) -> usize {
+    match highway {
+        Some(Highway::Tertiary) => 1,
+        Some(Highway::Secondary) | Some(Highway::SecondaryLink) => 2,
+        Some(Highway::Primary) => 3,
+        Some(Highway::Trunk) | Some(Highway::Motorway) => 4,
+        _ => 0,
+    }
+}
+
+/// Différence de cap entre -180 et 180 degrés, positive vers la droite
+pub fn turn_angle(from: &EdgePoint, to: &EdgePoint) -> f64 {
+    (to.bearing() - from.bearing() + 540.0) % 360.0 - 180.0
+}
+
+/// Protection de la traversée, d'après les arcs de part et d'autre du noeud
+fn crossing_factor(from: &EdgePoint, to: &EdgePoint) -> f64 {
+    let node = from.arrival_node().and_then(|node| node.crossing);
+    let crossings = [from.crossing, to.crossing, node];
+    if crossings.contains(&Some(Crossing::TrafficSignals)) {
+        SIGNALS_FACTOR
+    } else if crossings.contains(&Some(Crossing::Marked)) {
+        MARKED_FACTOR
+    } else {
+        1.0
+    }
+}
+
+/// Coût du passage de `from` à `to` au noeud où `from` arrive
+pub fn junction_cost(from: &EdgePoint, to: &EdgePoint) -> f64 {
+    let angle = turn_angle(from, to);
+    let on_route = arterial_rank(from.highway.as_ref()).max(arterial_rank(to.highway.as_ref()));
+    let junction = arterial_rank(from.junction()).max(on_route);
+    let factor = crossing_factor(from, to);
+
+    let mut cost = 0.0;
+    if angle.abs() >= 170.0 {
+        cost += U_TURN_PENALTY;
+    } else if angle.abs() >= 45.0 {
+        cost += TURN_PENALTY;
+        if angle < 0.0 {
+            cost += LEFT_TURN_PENALTIES[junction] * factor;
+        }
+    }
+    // On passe d'une rue locale à une autre à travers une artère
+    if junction > on_route {
+        cost += CROSSING_PENALTIES[junction] * factor;
+    }
+    cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Arc de 0.001° partant de (lon, lat) vers l'est, le nord, l'ouest ou le sud
+    fn arc(lon: f64, lat: f64, heading: char, highway: Option<Highway>) -> EdgePoint {
+        let (dlon, dlat) = match heading {
+            'E' => (0.001, 0.0),
+            'N' => (0.0, 0.001),
+            'W' => (-0.001, 0.0),
+            _ => (0.0, -0.001),
+        };
+        EdgePoint {
+            lon1: lon,
+            lat1: lat,
+            lon2: lon + dlon,
+            lat2: lat + dlat,
+            highway,
+            ..EdgePoint::default()
+        }
+    }
+
+    #[test]
+    fn test_turns() {
+        let from = arc(-73.001, 45.5, 'E', Some(Highway::Residential));
+        let straight = arc(-73.0, 45.5, 'E', Some(Highway::Residential));
+        let left = arc(-73.0, 45.5, 'N', Some(Highway::Residential));
+        let right = arc(-73.0, 45.5, 'S', Some(Highway::Residential));
+        let back = arc(-73.0, 45.5, 'W', Some(Highway::Residential));
+        assert_eq!(junction_cost(&from, &straight), 0.0);
+        assert_eq!(junction_cost(&from, &right), TURN_PENALTY);
+        assert_eq!(junction_cost(&from, &left), TURN_PENALTY);
+        assert_eq!(junction_cost(&from, &back), U_TURN_PENALTY);
+
+        // À gauche sur une artère: on coupe la circulation
+        let primary = arc(-73.0, 45.5, 'N', Some(Highway::Primary));
+        assert_eq!(junction_cost(&from, &primary), TURN_PENALTY + 100.0);
+        let right = arc(-73.0, 45.5, 'S', Some(Highway::Primary));
+        assert_eq!(junction_cost(&from, &right), TURN_PENALTY);
+    }
+
+    #[test]
+    fn test_crossing() {
+        let mut from = arc(-73.001, 45.5, 'E', Some(Highway::Residential));
+        from.junction_target = Some(Highway::Secondary);
+        let mut to = arc(-73.0, 45.5, 'E', Some(Highway::Residential));
+        assert_eq!(junction_cost(&from, &to), 60.0);
+        to.crossing = Some(Crossing::TrafficSignals);
+        assert_eq!(junction_cost(&from, &to), 15.0);
+        // Sans artère au noeud
+        from.junction_target = None;
+        assert_eq!(junction_cost(&from, &to), 0.0);
+    }
+}
@@ -0,0 +1,299 @@
+//! Niveau de stress de la circulation (Level of Traffic Stress, Mekuria et Furth 2012),
+//! de 1 (tout le monde, enfants compris) à 4 (cyclistes aguerris seulement).
+//! Le niveau est calculé à partir des tags OSM du way: classe de route, `maxspeed`,
+//! `lanes`, stationnement et type d'aménagement cyclable. Le côté de la rue n'est pas
+//! considéré: on retient le meilleur aménagement présent.
+//!
+//! Le niveau de chaque way est enregistré dans la table `way_lts` au chargement du graphe
+//! (`graph::reload`): les tuiles `bike_path` le lisent au lieu de le recalculer.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::db::edge::Point;
+use crate::db::utils::distance_meters;
+
+pub const LTS_MAX: u8 = 4;
+
+const CYCLEWAY_KEYS: [&str; 4] = [
+    "cycleway",
+    "cycleway:left",
+    "cycleway:right",
+    "cycleway:both",
+];
+const PARKING_KEYS: [&str; 6] = [
+    "parking:both",
+    "parking:left",
+    "parking:right",
+    "parking:lane:both",
+    "parking:lane:left",
+    "parking:lane:right",
+];
+
+/// Vitesse en km/h: "50", "50 km/h", "30 mph" ou une valeur implicite comme "CA-QC:urban"
+pub fn parse_maxspeed(value: &str) -> Option<f64> {
+    let value = value.split(';').next()?.trim();
+    if let Some(mph) = value.strip_suffix("mph") {
+        return mph.trim().parse::<f64>().ok().map(|mph| mph * 1.609);
+    }
+    if let Ok(kmh) = value.trim_end_matches("km/h").trim().parse::<f64>() {
+        return Some(kmh);
+    }
+    match value.rsplit(':').next()? {
+        "walk" => Some(10.0),
+        "living_street" => Some(20.0),
+        "urban" => Some(50.0),
+        "rural" => Some(90.0),
+        "motorway" | "none" => Some(100.0),
+        _ => None,
+    }
+}
+
+/// Vitesse affichée la plus courante au Québec quand `maxspeed` manque
+fn default_maxspeed(highway: &str) -> f64 {
+    match highway {
+        "living_street" => 20.0,
+        "service" => 30.0,
+        "residential" => 40.0,
+        _ => 50.0,
+    }
+}
+
+fn default_lanes(highway: &str) -> u32 {
+    match highway {
+        "primary" | "primary_link" => 4,
+        _ => 2,
+    }
+}
+
+/// Rue locale, où le débit de véhicules est faible
+fn is_local(highway: &str) -> bool {
+    matches!(
+        highway,
+        "residential" | "service" | "unclassified" | "living_street" | "road"
+    )
+}
+
+/// Voies sans circulation motorisée
+fn is_car_free(highway: &str) -> bool {
+    matches!(
+        highway,
+        "cycleway"
+            | "path"
+            | "footway"
+            | "pedestrian"
+            | "steps"
+            | "bridleway"
+            | "track"
+            | "corridor"
+            | "living_street"
+    )
+}
+
+/// Stationnement sur rue d'au moins un côté
+fn has_parking(tags: &HashMap<String, String>) -> bool {
+    PARKING_KEYS.iter().any(|key| {
+        tags.get(*key).is_some_and(|value| {
+            !matches!(
+                value.as_str(),
+                "no" | "no_parking" | "no_stopping" | "separate" | "fire_lane"
+            )
+        })
+    })
+}
+
+/// LTS de 1 à 4 d'un way d'après ses tags
+pub fn classify(tags: &HashMap<String, String>) -> u8 {
+    let get = |key: &str| tags.get(key).map(|value| value.as_str());
+    let cycleways: Vec<&str> = CYCLEWAY_KEYS.iter().filter_map(|key| get(key)).collect();
+
+    if get("route") == Some("ferry") {
+        return 1;
+    }
+    let Some(highway) = get("highway") else {
+        return 1;
+    };
+    if is_car_free(highway)
+        || get("cyclestreet") == Some("yes")
+        || get("bicycle_road") == Some("yes")
+        || cycleways.contains(&"track")
+    {
+        return 1;
+    }
+    if matches!(
+        highway,
+        "motorway" | "motorway_link" | "trunk" | "trunk_link"
+    ) {
+        return 4;
+    }
+
+    let speed = get("maxspeed")
+        .and_then(parse_maxspeed)
+        .unwrap_or_else(|| default_maxspeed(highway));
+    let lanes = get("lanes")
+        .and_then(|lanes| lanes.split(';').next()?.trim().parse::<u32>().ok())
+        .filter(|lanes| *lanes > 0)
+        .unwrap_or_else(|| default_lanes(highway));
+    let lanes_per_direction = if matches!(get("oneway"), Some("yes") | Some("-1")) {
+        lanes
+    } else {
+        lanes.div_ceil(2)
+    };
+    let bike_lane = cycleways
+        .iter()
+        .any(|cycleway| matches!(*cycleway, "lane" | "opposite_lane" | "share_busway"));
+
+    if bike_lane {
+        match (lanes_per_direction, has_parking(tags)) {
+            (2.., _) if speed <= 65.0 => 3,
+            (2.., _) => 4,
+            (_, true) if speed <= 40.0 => 1,
+            (_, true) if speed <= 50.0 => 2,
+            (_, true) => 3,
+            (_, false) if speed <= 50.0 => 1,
+            (_, false) if speed <= 65.0 => 2,
+            (_, false) => 3,
+        }
+    } else if lanes_per_direction >= 2 {
+        if speed <= 40.0 {
+            3
+        } else {
+            4
+        }
+    } else if speed <= 30.0 {
+        1
+    } else if speed <= 40.0 {
+        if is_local(highway) {
+            1
+        } else {
+            2
+        }
+    } else if speed <= 50.0 {
+        if is_local(highway) {
+            2
+        } else {
+            3
+        }
+    } else {
+        4
+    }
+}
+
+/// Distance parcourue à chaque niveau de stress le long d'un itinéraire
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct LtsBreakdown {
+    /// Mètres en LTS 1, 2, 3 et 4
+    pub distances: [f64; LTS_MAX as usize],
+    /// Part du trajet en LTS 1 ou 2, entre 0 et 1
+    pub low_stress: f64,
+}
+
+impl LtsBreakdown {
+    /// Les points ajoutés à la main (départ, arrivée) ne sont pas comptés
+    pub fn from_points(points: &[Point]) -> LtsBreakdown {
+        let mut distances = [0.0; LTS_MAX as usize];
+        for (previous, point) in points.iter().zip(points.iter().skip(1)) {
+            if let Some(edge) = &point.edge {
+                let level = edge.lts.clamp(1, LTS_MAX) as usize;
+                distances[level - 1] +=
+                    distance_meters(previous.lat, previous.lng, point.lat, point.lng);
+            }
+        }
+        let total: f64 = distances.iter().sum();
+        let low_stress = if total > 0.0 {
+            (distances[0] + distances[1]) / total
+        } else {
+            0.0
+        };
+        LtsBreakdown {
+            distances,
+            low_stress,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::EdgePoint;
+    use std::sync::Arc;
+
+    fn tags(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_maxspeed() {
+        assert_eq!(parse_maxspeed("50"), Some(50.0));
+        assert_eq!(parse_maxspeed("40 km/h"), Some(40.0));
+        assert_eq!(parse_maxspeed("30 mph").map(f64::round), Some(48.0));
+        assert_eq!(parse_maxspeed("CA-QC:urban"), Some(50.0));
+        assert_eq!(parse_maxspeed("signals"), None);
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&tags(&[("highway", "cycleway")])), 1);
+        assert_eq!(
+            classify(&tags(&[
+                ("highway", "primary"),
+                ("cycleway:right", "track")
+            ])),
+            1
+        );
+        assert_eq!(classify(&tags(&[("highway", "residential")])), 1);
+        assert_eq!(
+            classify(&tags(&[("highway", "residential"), ("maxspeed", "50")])),
+            2
+        );
+        assert_eq!(classify(&tags(&[("highway", "tertiary")])), 3);
+        assert_eq!(classify(&tags(&[("highway", "primary")])), 4);
+        assert_eq!(classify(&tags(&[("highway", "trunk")])), 4);
+
+        // Bande cyclable: le stationnement et le nombre de voies comptent
+        let lane = [("highway", "secondary"), ("cycleway:right", "lane")];
+        assert_eq!(classify(&tags(&lane)), 1);
+        let parking = [lane[0], lane[1], ("parking:left", "lane")];
+        assert_eq!(classify(&tags(&parking)), 2);
+        let no_parking = [lane[0], lane[1], ("parking:both", "no")];
+        assert_eq!(classify(&tags(&no_parking)), 1);
+        let wide = [lane[0], lane[1], ("lanes", "4")];
+        assert_eq!(classify(&tags(&wide)), 3);
+        let oneway = [("highway", "tertiary"), ("lanes", "2"), ("oneway", "yes")];
+        assert_eq!(classify(&tags(&oneway)), 4);
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let point = |lng: f64, lts: Option<u8>| Point {
+            lng,
+            lat: 45.5,
+            way_id: 0,
+            node_id: 0,
+            length: 0.0,
+            ferry: false,
+            name: None,
+            edge: lts.map(|lts| {
+                Arc::new(EdgePoint {
+                    lts,
+                    ..EdgePoint::default()
+                })
+            }),
+        };
+        let points = [
+            point(-73.6, None),
+            point(-73.59, Some(1)),
+            point(-73.58, Some(2)),
+            point(-73.57, Some(4)),
+            point(-73.56, Some(3)),
+        ];
+        let breakdown = LtsBreakdown::from_points(&points);
+        assert!(breakdown.distances.iter().all(|distance| *distance > 700.0));
+        assert!((breakdown.low_stress - 0.5).abs() < 0.01);
+        assert_eq!(LtsBreakdown::from_points(&[]).low_stress, 0.0);
+    }
+}
@@ -1,5 +1,16 @@
+pub mod alternative;
+pub mod avoid;
 pub mod cost;
 pub mod elevation;
+pub mod export;
 pub mod import;
+pub mod infrastructure;
+pub mod instructions;
+pub mod isochrone;
+pub mod junction;
+pub mod lts;
 pub mod mtl;
 pub mod proxy;
+pub mod round_trip;
+pub mod speed;
+pub mod touring;
@@ -1,14 +1,19 @@
+use chrono::NaiveDate;
 use futures::{stream, StreamExt};
-use geojson::GeoJson;
+use geojson::{Feature, GeoJson};
 use reqwest;
 use sqlx::postgres::Postgres;
 use std::fs::File;
 use std::io::Write;
 use std::process::Command;
 use std::str::FromStr;
-use time::Date;
 
-use crate::db::road_work::{self, Roadwork};
+use crate::db::road_work::{self, parse_departure, Roadwork};
+
+/// Propriétés de début et de fin d'une entrave (jeu de données Info-Travaux de Montréal),
+/// en UTC: `2024-06-03T04:00:00Z` est le 3 juin à minuit à Montréal
+const START_DATE_PROPERTY: &str = "duration_start_date";
+const END_DATE_PROPERTY: &str = "duration_end_date";
 
 pub struct SphericalMercator {
     zoom: u32,
@@ -101,23 +106,13 @@ pub async fn read_tile(sm: &SphericalMercator, conn: &sqlx::Pool<Postgres>) {
     };
     if let GeoJson::FeatureCollection(features) = geojson {
         for feature in features {
+            let start_date = property_date(&feature, START_DATE_PROPERTY);
+            let end_date = property_date(&feature, END_DATE_PROPERTY);
             if let Some(geometry) = feature.geometry {
                 let road_work = road_work::Roadwork {
                     geom: geometry,
-                    start_date: match Date::from_calendar_date(2024, time::Month::April, 1) {
-                        Ok(d) => d,
-                        Err(e) => {
-                            println!("Error creating date: {}", e);
-                            return;
-                        }
-                    },
-                    end_date: match Date::from_calendar_date(2024, time::Month::April, 1) {
-                        Ok(d) => d,
-                        Err(e) => {
-                            println!("Error creating date: {}", e);
-                            return;
-                        }
-                    },
+                    start_date,
+                    end_date,
                 };
                 road_work.insert(conn).await;
             }
@@ -125,16 +120,47 @@ pub async fn read_tile(sm: &SphericalMercator, conn: &sqlx::Pool<Postgres>) {
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use std::env;
+/// Jour, à Montréal, de la date de la propriété `key`
+fn property_date(feature: &Feature, key: &str) -> Option<NaiveDate> {
+    parse_departure(feature.property(key)?.as_str()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-//     #[tokio::test]
-//     async fn read_mtl_work() {
-//         let conn = sqlx::Pool::connect(&env::var("DATABASE_URL").unwrap())
-//             .await
-//             .unwrap();
+    /// Entrave telle que convertie par ogr2ogr depuis une tuile `entraves-polygonales`
+    const FEATURE: &str = r#"{
+        "type": "Feature",
+        "properties": {
+            "id": "a3f0c6de-7d2b-4f0e-9b51-2c8d4f1e6a90",
+            "boroughid": "Le Plateau-Mont-Royal",
+            "currentstatus": "Permis délivré",
+            "duration_start_date": "2024-06-03T04:00:00Z",
+            "duration_end_date": "2024-06-28T03:59:59Z",
+            "reason_category": "Construction/rénovation sans excavation",
+            "occupancy_name": "Rue Rachel Est",
+            "submittercategory": "Entrepreneur",
+            "organizationname": "Construction ABC inc."
+        },
+        "geometry": {
+            "type": "Polygon",
+            "coordinates": [[[-73.5801, 45.5252], [-73.5795, 45.5249],
+                [-73.5793, 45.5251], [-73.5799, 45.5254], [-73.5801, 45.5252]]]
+        }
+    }"#;
 
-//         super::fetch_montreal_data(&conn).await;
-//     }
-// }    
+    #[test]
+    fn test_property_date() {
+        let feature = Feature::from_str(FEATURE).unwrap();
+        assert_eq!(
+            property_date(&feature, START_DATE_PROPERTY),
+            NaiveDate::from_ymd_opt(2024, 6, 3)
+        );
+        assert_eq!(
+            property_date(&feature, END_DATE_PROPERTY),
+            NaiveDate::from_ymd_opt(2024, 6, 27)
+        );
+        assert_eq!(property_date(&feature, "date_fin"), None);
+    }
+}
@@ -0,0 +1,232 @@
+//! Génération de boucles (aller-retour par un autre chemin) d'une distance cible.
+//! Chaque boucle est un triangle départ -> sommet 1 -> sommet 2 -> départ dont les côtés
+//! sont calculés avec le profil de coût. Les edges déjà utilisés par un côté précédent
+//! sont pénalisés pour limiter les passages répétés sur les mêmes rues.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use sqlx::Postgres;
+
+use crate::db::edge::{Edge, Point};
+use crate::db::utils::{destination, distance_meters};
+use crate::utils::cost::{CostProfile, HPenalty};
+
+/// Distance cible minimale et maximale d'une boucle en mètres
+pub const MIN_LOOP_DISTANCE: f64 = 2000.0;
+pub const MAX_LOOP_DISTANCE: f64 = 200000.0;
+/// Nombre de boucles candidates par défaut
+pub const DEFAULT_CANDIDATES: usize = 4;
+pub const MAX_CANDIDATES: usize = 8;
+/// Longueur cumulée maximale des boucles candidates d'une requête, en mètres
+const MAX_SEARCH_DISTANCE: f64 = 400000.0;
+/// Rapport moyen entre la distance sur le réseau et la distance à vol d'oiseau
+const DETOUR_FACTOR: f64 = 1.3;
+/// Pénalité appliquée aux edges déjà empruntés par la boucle
+const REUSE_PENALTY: f64 = 5.0;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RoundTrip {
+    #[serde(skip)]
+    pub points: Vec<Point>,
+    /// Cap (degrés) du premier sommet de la boucle
+    pub bearing: f64,
+    /// Longueur réelle en mètres
+    pub length: f64,
+    /// Fraction de la longueur parcourue plus d'une fois
+    pub reused: f64,
+}
+
+/// Sommets (lng, lat) d'une boucle triangulaire équilatérale partant de `start`
+/// dans la direction `bearing`, pour une distance cible en mètres.
+pub fn triangle_vertices(start: (f64, f64), bearing: f64, distance: f64) -> [(f64, f64); 2] {
+    let side = distance / (3.0 * DETOUR_FACTOR);
+    let (lng, lat) = start;
+    let (lat1, lng1) = destination(lat, lng, bearing, side);
+    let (lat2, lng2) = destination(lat, lng, (bearing + 60.0) % 360.0, side);
+    [(lng1, lat1), (lng2, lat2)]
+}
+
+/// Caps de départ des boucles candidates, répartis uniformément
+pub fn candidate_bearings(candidates: usize) -> Vec<f64> {
+    (0..candidates)
+        .map(|i| i as f64 * 360.0 / candidates as f64)
+        .collect()
+}
+
+/// Nombre maximal de boucles candidates pour une distance cible en mètres:
+/// les longues boucles sont moins nombreuses pour borner le temps de calcul
+pub fn max_candidates(distance: f64) -> usize {
+    ((MAX_SEARCH_DISTANCE / distance) as usize).clamp(1, MAX_CANDIDATES)
+}
+
+/// Longueur totale et fraction de la longueur passant sur un edge déjà parcouru
+pub fn loop_stats(points: &[Point]) -> (f64, f64) {
+    let mut seen = HashSet::new();
+    let mut length = 0.0;
+    let mut reused = 0.0;
+    for (previous, point) in points.iter().zip(points.iter().skip(1)) {
+        let distance = distance_meters(previous.lat, previous.lng, point.lat, point.lng);
+        length += distance;
+        if let Some(edge) = &point.edge {
+            if !seen.insert(edge.id) {
+                reused += distance;
+            }
+        }
+    }
+    let reused = if length > 0.0 { reused / length } else { 0.0 };
+    (length, reused)
+}
+
+/// Calcule une boucle partant de `start_node_id` et passant par les sommets donnés.
+/// Retourne None si un des côtés est introuvable.
+pub async fn build_loop(
+    start_node_id: i64,
+    vertex_node_ids: &[i64],
+    profile: &CostProfile,
+    allow_ferry: bool,
+    conn: &sqlx::Pool<Postgres>,
+) -> Option<Vec<Point>> {
+    let node_ids: Vec<i64> = std::iter::once(start_node_id)
+        .chain(vertex_node_ids.iter().copied())
+        .chain(std::iter::once(start_node_id))
+        .collect();
+    let mut used = HashSet::new();
+    let mut points: Vec<Point> = vec![];
+    for (start, end) in node_ids.iter().zip(node_ids.iter().skip(1)) {
+        if start == end {
+            continue;
+        }
+        // Le premier côté n'a rien à pénaliser et peut passer par la hiérarchie de contraction
+        let leg = if used.is_empty() {
+            Edge::route(*start, *end, profile, conn, None, allow_ferry).await
+        } else {
+            let h = HPenalty {
+                inner: profile.h(),
+                penalized: used.clone(),
+                factor: REUSE_PENALTY,
+            };
+            Edge::a_star_bidirectional(*start, *end, Box::new(h), conn, None, allow_ferry).await
+        };
+        if leg.is_empty() {
+            return None;
+        }
+        used.extend(
+            leg.iter()
+                .filter_map(|point| point.edge.as_ref().map(|edge| edge.id)),
+        );
+        // Le premier point d'un côté est le dernier point du côté précédent
+        let skip = if points.is_empty() { 0 } else { 1 };
+        points.extend(leg.into_iter().skip(skip));
+    }
+    Some(points)
+}
+
+/// Génère jusqu'à `candidates` boucles autour de `start`, triées par écart à la distance cible.
+pub async fn find_round_trips(
+    start: (f64, f64),
+    distance: f64,
+    candidates: usize,
+    profile: &CostProfile,
+    allow_ferry: bool,
+    conn: &sqlx::Pool<Postgres>,
+) -> Result<Vec<RoundTrip>, String> {
+    let (start_lng, start_lat) = start;
+    let start_node = Edge::find_closest_node(&start_lng, &start_lat, conn)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error while fetching start node for {}, {}: {}",
+                start_lng, start_lat, e
+            )
+        })?;
+
+    let mut round_trips = vec![];
+    for bearing in candidate_bearings(candidates) {
+        let mut vertex_node_ids = vec![];
+        for (lng, lat) in triangle_vertices(start, bearing, distance) {
+            // Un sommet sans route à proximité (lac, fleuve) est simplement ignoré
+            if let Ok(node) = Edge::find_closest_node(&lng, &lat, conn).await {
+                vertex_node_ids.push(node.node_id);
+            }
+        }
+        if vertex_node_ids.is_empty() {
+            continue;
+        }
+        let Some(points) = build_loop(
+            start_node.node_id,
+            &vertex_node_ids,
+            profile,
+            allow_ferry,
+            conn,
+        )
+        .await
+        else {
+            continue;
+        };
+        let (length, reused) = loop_stats(&points);
+        round_trips.push(RoundTrip {
+            points,
+            bearing,
+            length,
+            reused,
+        });
+    }
+    round_trips.sort_by(|a, b| {
+        (a.length - distance)
+            .abs()
+            .total_cmp(&(b.length - distance).abs())
+    });
+    Ok(round_trips)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::EdgePoint;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_triangle_vertices() {
+        let start = (-73.6, 45.5);
+        let [first, second] = triangle_vertices(start, 0.0, 30000.0);
+        let side = 30000.0 / (3.0 * DETOUR_FACTOR);
+        let d1 = distance_meters(start.1, start.0, first.1, first.0);
+        let d2 = distance_meters(start.1, start.0, second.1, second.0);
+        let d3 = distance_meters(first.1, first.0, second.1, second.0);
+        assert!((d1 - side).abs() < 1.0);
+        assert!((d2 - side).abs() < 1.0);
+        assert!((d3 - side).abs() < 10.0);
+        // Premier sommet au nord du départ
+        assert!(first.1 > start.1);
+    }
+
+    #[test]
+    fn test_max_candidates() {
+        assert_eq!(max_candidates(MIN_LOOP_DISTANCE), MAX_CANDIDATES);
+        assert_eq!(max_candidates(100000.0), 4);
+        assert_eq!(max_candidates(MAX_LOOP_DISTANCE), 2);
+    }
+
+    #[test]
+    fn test_loop_stats() {
+        let point = |lat: f64, id: i64| Point {
+            lng: -73.0,
+            lat,
+            way_id: 0,
+            node_id: 0,
+            length: 0.0,
+            ferry: false,
+            name: None,
+            edge: Some(Arc::new(EdgePoint {
+                id,
+                ..EdgePoint::default()
+            })),
+        };
+        // Aller-retour sur le même edge
+        let points = vec![point(45.0, 1), point(45.01, 1), point(45.0, 1)];
+        let (length, reused) = loop_stats(&points);
+        assert!((length - 2224.0).abs() < 5.0);
+        assert_eq!(reused, 0.5);
+    }
+}
@@ -0,0 +1,248 @@
+//! Modèle de vitesse pour estimer la durée d'un itinéraire à vélo.
+//! Tient compte de la pente, du revêtement, des traverses, des segments où il faut
+//! marcher à côté du vélo et du temps d'embarquement des traversiers.
+
+use crate::db::edge::{
+    Bicycle, Cycleway, EdgePoint, Footway, Highway, Point, Route, Smoothness, SourceOrTarget,
+    Surface,
+};
+use crate::db::utils::distance_meters;
+use crate::utils::cost::BikeType;
+use crate::utils::elevation::calculate_slope_percentage;
+
+/// Vitesse de croisière par défaut en km/h
+pub const DEFAULT_CRUISING_SPEED: f64 = 15.0;
+/// Vitesse de croisière par défaut d'un vélo à assistance électrique en km/h
+pub const EBIKE_CRUISING_SPEED: f64 = 22.0;
+/// Vitesse de croisière par défaut d'un vélo cargo ou avec remorque en km/h
+pub const CARGO_CRUISING_SPEED: f64 = 12.0;
+/// Vitesse de croisière par défaut d'un vélo de route en km/h
+pub const ROAD_CRUISING_SPEED: f64 = 25.0;
+const MIN_CRUISING_SPEED: f64 = 5.0;
+const MAX_CRUISING_SPEED: f64 = 50.0;
+/// Vitesse de marche à côté du vélo en km/h
+const WALKING_SPEED: f64 = 4.0;
+/// Vitesse dans les escaliers en km/h
+const STEPS_SPEED: f64 = 2.0;
+/// Vitesse moyenne d'un traversier en km/h
+const FERRY_SPEED: f64 = 15.0;
+/// Attente et embarquement au traversier en secondes
+const FERRY_BOARDING_TIME: f64 = 15.0 * 60.0;
+/// Attente moyenne à une traverse en secondes
+const CROSSING_DELAY: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpeedModel {
+    /// Vitesse sur terrain plat et revêtement lisse en km/h
+    pub cruising_speed: f64,
+    /// Assistance électrique: les montées ralentissent beaucoup moins
+    pub assisted: bool,
+}
+
+impl Default for SpeedModel {
+    fn default() -> Self {
+        SpeedModel {
+            cruising_speed: DEFAULT_CRUISING_SPEED,
+            assisted: false,
+        }
+    }
+}
+
+impl SpeedModel {
+    pub fn new(cruising_speed: Option<f64>) -> Result<SpeedModel, String> {
+        match cruising_speed {
+            None => Ok(SpeedModel::default()),
+            Some(speed) if (MIN_CRUISING_SPEED..=MAX_CRUISING_SPEED).contains(&speed) => {
+                Ok(SpeedModel {
+                    cruising_speed: speed,
+                    assisted: false,
+                })
+            }
+            Some(speed) => Err(format!(
+                "Invalid speed {}: must be between {} and {} km/h",
+                speed, MIN_CRUISING_SPEED, MAX_CRUISING_SPEED
+            )),
+        }
+    }
+
+    /// Modèle de vitesse d'un type de vélo; `cruising_speed` remplace la vitesse par défaut du type
+    pub fn for_bike(bike: BikeType, cruising_speed: Option<f64>) -> Result<SpeedModel, String> {
+        let default_speed = match bike {
+            BikeType::Ebike => EBIKE_CRUISING_SPEED,
+            BikeType::Cargo => CARGO_CRUISING_SPEED,
+            BikeType::Road => ROAD_CRUISING_SPEED,
+            BikeType::Regular | BikeType::Touring => DEFAULT_CRUISING_SPEED,
+        };
+        let mut model = SpeedModel::new(cruising_speed.or(Some(default_speed)))?;
+        model.assisted = bike == BikeType::Ebike;
+        Ok(model)
+    }
+
+    /// Pente (%) dans le sens de parcours du segment
+    fn slope(edge: &EdgePoint) -> f64 {
+        match (edge.elevation_start, edge.elevation_end) {
+            (Some(start), Some(end)) => match edge.direction {
+                SourceOrTarget::Target => calculate_slope_percentage(start, end, edge.length),
+                SourceOrTarget::Source => calculate_slope_percentage(end, start, edge.length),
+            },
+            _ => 0.0,
+        }
+    }
+
+    fn slope_factor(&self, slope: f64) -> f64 {
+        if slope > 0.0 {
+            let climbing_penalty = if self.assisted { 0.04 } else { 0.12 };
+            1.0 / (1.0 + slope * climbing_penalty)
+        } else {
+            (1.0 - slope * 0.05).min(1.5)
+        }
+    }
+
+    fn surface_factor(edge: &EdgePoint) -> f64 {
+        let surface = match edge.surface {
+            Some(Surface::Gravel) | Some(Surface::FineGravel) | Some(Surface::Compacted) => 0.8,
+            Some(Surface::Sett) | Some(Surface::Cobblestone) => 0.7,
+            Some(Surface::UnhewnCobblestone) => 0.6,
+            Some(Surface::Chipseal) | Some(Surface::PavingStones) => 0.95,
+            Some(Surface::Wood) | Some(Surface::Metal) => 0.9,
+            Some(Surface::Pebblestone)
+            | Some(Surface::Unpaved)
+            | Some(Surface::Dirt)
+            | Some(Surface::Ground)
+            | Some(Surface::Earth)
+            | Some(Surface::Woodchips) => 0.7,
+            Some(Surface::Mud) | Some(Surface::Grass) | Some(Surface::Sand) => 0.5,
+            Some(Surface::Asphalt) | Some(Surface::Concrete) | Some(Surface::Paved) | None => 1.0,
+        };
+        let smoothness = match edge.smoothness {
+            Some(Smoothness::Bad) => 0.8,
+            Some(Smoothness::VeryBad) => 0.7,
+            Some(Smoothness::Horrible)
+            | Some(Smoothness::VeryHorrible)
+            | Some(Smoothness::Impassable) => 0.5,
+            Some(Smoothness::Excellent)
+            | Some(Smoothness::Good)
+            | Some(Smoothness::Intermediate)
+            | None => 1.0,
+        };
+        surface * smoothness
+    }
+
+    /// Vitesse en km/h sur un segment
+    pub fn edge_speed(&self, edge: &EdgePoint) -> f64 {
+        if edge.route == Some(Route::Ferry) {
+            return FERRY_SPEED;
+        }
+        if edge.highway == Some(Highway::Steps) {
+            return STEPS_SPEED;
+        }
+        if edge.bicycle == Some(Bicycle::Dismount) {
+            return WALKING_SPEED;
+        }
+        let speed = self.cruising_speed
+            * self.slope_factor(SpeedModel::slope(edge))
+            * SpeedModel::surface_factor(edge);
+        speed.max(WALKING_SPEED)
+    }
+
+    /// Durée en secondes pour parcourir `distance` mètres du segment
+    pub fn edge_duration(&self, edge: &EdgePoint, distance: f64) -> f64 {
+        let mut duration = distance / (self.edge_speed(edge) / 3.6);
+        if edge.cycleway == Some(Cycleway::Crossing) || edge.footway == Some(Footway::Crossing) {
+            duration += CROSSING_DELAY;
+        }
+        duration
+    }
+
+    /// Durée totale en secondes d'une suite de points
+    pub fn route_duration(&self, points: &[Point]) -> f64 {
+        let mut duration = 0.0;
+        let mut on_ferry = false;
+        for (previous, point) in points.iter().zip(points.iter().skip(1)) {
+            let distance = distance_meters(previous.lat, previous.lng, point.lat, point.lng);
+            match &point.edge {
+                Some(edge) => {
+                    let ferry = edge.route == Some(Route::Ferry);
+                    if ferry && !on_ferry {
+                        duration += FERRY_BOARDING_TIME;
+                    }
+                    on_ferry = ferry;
+                    duration += self.edge_duration(edge, distance);
+                }
+                None => duration += distance / (self.cruising_speed / 3.6),
+            }
+        }
+        duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_speed_model() {
+        let model = SpeedModel::default();
+        let flat = EdgePoint {
+            length: 100.0,
+            ..EdgePoint::default()
+        };
+        assert_eq!(model.edge_speed(&flat), DEFAULT_CRUISING_SPEED);
+
+        let uphill = EdgePoint {
+            length: 100.0,
+            elevation_start: Some(100),
+            elevation_end: Some(105),
+            ..EdgePoint::default()
+        };
+        let downhill = uphill.reverse();
+        assert!(model.edge_speed(&uphill) < DEFAULT_CRUISING_SPEED);
+        assert!(model.edge_speed(&downhill) > DEFAULT_CRUISING_SPEED);
+
+        let gravel = EdgePoint {
+            surface: Some(Surface::Gravel),
+            ..EdgePoint::default()
+        };
+        assert!(model.edge_speed(&gravel) < DEFAULT_CRUISING_SPEED);
+
+        let dismount = EdgePoint {
+            bicycle: Some(Bicycle::Dismount),
+            ..EdgePoint::default()
+        };
+        assert_eq!(model.edge_speed(&dismount), WALKING_SPEED);
+
+        let ebike = SpeedModel::new(Some(25.0)).unwrap();
+        assert_eq!(ebike.edge_speed(&flat), 25.0);
+        let assisted = SpeedModel::for_bike(BikeType::Ebike, None).unwrap();
+        assert_eq!(assisted.edge_speed(&flat), EBIKE_CRUISING_SPEED);
+        assert!(
+            assisted.edge_speed(&uphill) / EBIKE_CRUISING_SPEED
+                > model.edge_speed(&uphill) / DEFAULT_CRUISING_SPEED
+        );
+        assert!(SpeedModel::new(Some(120.0)).is_err());
+    }
+
+    #[test]
+    fn test_ferry_boarding() {
+        let ferry = Some(Arc::new(EdgePoint {
+            route: Some(Route::Ferry),
+            ..EdgePoint::default()
+        }));
+        let points: Vec<Point> = (0..3)
+            .map(|i| Point {
+                lng: -73.0,
+                lat: 45.0 + i as f64 * 0.01,
+                way_id: 0,
+                node_id: 0,
+                length: 0.0,
+                ferry: true,
+                name: None,
+                edge: ferry.clone(),
+            })
+            .collect();
+        let duration = SpeedModel::default().route_duration(&points);
+        // ~2.2 km à 15 km/h + un seul embarquement
+        assert!((duration - (FERRY_BOARDING_TIME + 2224.0 / (FERRY_SPEED / 3.6))).abs() < 5.0);
+    }
+}
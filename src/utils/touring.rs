@@ -0,0 +1,223 @@
+//! Cyclotourisme: découpage d'un long itinéraire en étapes quotidiennes
+//! dont les fins sont ramenées vers les villes traversées (table `city`).
+
+use serde::Serialize;
+use sqlx::Postgres;
+
+use crate::db::city::City;
+use crate::db::edge::{EdgePoint, Point};
+use crate::db::utils::distance_meters;
+use crate::utils::cost::{get_infrastructure, Infrastructure};
+use crate::utils::elevation::get_elevation_profile;
+use crate::utils::speed::SpeedModel;
+
+/// Longueur d'étape par défaut, minimale et maximale en mètres
+pub const DEFAULT_STAGE_LENGTH: f64 = 80000.0;
+pub const MIN_STAGE_LENGTH: f64 = 20000.0;
+pub const MAX_STAGE_LENGTH: f64 = 200000.0;
+/// Fraction de la longueur d'étape dans laquelle on peut déplacer la fin d'étape vers une ville
+const SNAP_WINDOW: f64 = 0.2;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Stage {
+    /// Index du premier et du dernier point de l'étape dans l'itinéraire
+    pub start_index: usize,
+    pub end_index: usize,
+    /// Ville où se termine l'étape, si une ville est assez proche
+    pub end_city: Option<String>,
+    /// Distance en mètres
+    pub distance: f64,
+    /// Dénivelé positif en mètres
+    pub ascent: f64,
+    /// Durée estimée en secondes
+    pub duration: f64,
+    /// Part de la distance sur des aménagements cyclables dédiés (0 à 1)
+    pub dedicated_share: f64,
+}
+
+/// Piste cyclable ou bande cyclable, selon la classification du modèle de coût
+pub fn is_dedicated(edge: &EdgePoint) -> bool {
+    matches!(
+        get_infrastructure(edge),
+        Infrastructure::Track | Infrastructure::Lane
+    )
+}
+
+/// Distance cumulée en mètres à chaque point
+pub fn cumulative_distances(points: &[Point]) -> Vec<f64> {
+    let mut total = 0.0;
+    std::iter::once(0.0)
+        .chain(points.windows(2).map(|w| {
+            total += distance_meters(w[0].lat, w[0].lng, w[1].lat, w[1].lng);
+            total
+        }))
+        .collect()
+}
+
+/// Index des points où la distance cumulée atteint chaque multiple de `stage_length`.
+/// Le dernier point termine toujours la dernière étape.
+pub fn stage_breaks(cumulative: &[f64], stage_length: f64) -> Vec<usize> {
+    let Some(&total) = cumulative.last() else {
+        return vec![];
+    };
+    let mut breaks = vec![];
+    let mut target = stage_length;
+    // Une dernière étape trop courte est fusionnée avec la précédente
+    while target + stage_length * SNAP_WINDOW < total {
+        if let Some(index) = cumulative.iter().position(|d| *d >= target) {
+            breaks.push(index);
+        }
+        target += stage_length;
+    }
+    breaks.push(cumulative.len() - 1);
+    breaks
+}
+
+/// Point de l'itinéraire le plus proche de `city`, à moins de `window` mètres de `index`
+pub fn snap_to_city(
+    points: &[Point],
+    cumulative: &[f64],
+    index: usize,
+    window: f64,
+    city: &City,
+) -> usize {
+    (0..points.len())
+        .filter(|i| (cumulative[*i] - cumulative[index]).abs() <= window)
+        .min_by(|a, b| {
+            let da = distance_meters(points[*a].lat, points[*a].lng, city.lat, city.lng);
+            let db = distance_meters(points[*b].lat, points[*b].lng, city.lat, city.lng);
+            da.total_cmp(&db)
+        })
+        .unwrap_or(index)
+}
+
+fn stage(
+    points: &[Point],
+    start_index: usize,
+    end_index: usize,
+    end_city: Option<String>,
+    speed_model: &SpeedModel,
+) -> Stage {
+    let stage_points = &points[start_index..=end_index];
+    let (distance, dedicated) =
+        stage_points
+            .windows(2)
+            .fold((0.0, 0.0), |(distance, dedicated), w| {
+                let d = distance_meters(w[0].lat, w[0].lng, w[1].lat, w[1].lng);
+                let is_dedicated = w[1].edge.as_ref().is_some_and(|edge| is_dedicated(edge));
+                (distance + d, dedicated + if is_dedicated { d } else { 0.0 })
+            });
+    Stage {
+        start_index,
+        end_index,
+        end_city,
+        distance,
+        ascent: get_elevation_profile(stage_points).ascent,
+        duration: speed_model.route_duration(stage_points),
+        dedicated_share: if distance > 0.0 {
+            dedicated / distance
+        } else {
+            0.0
+        },
+    }
+}
+
+/// Découpe l'itinéraire en étapes d'environ `stage_length` mètres se terminant dans une ville.
+pub async fn split_stages(
+    points: &[Point],
+    stage_length: f64,
+    speed_model: &SpeedModel,
+    conn: &sqlx::Pool<Postgres>,
+) -> Vec<Stage> {
+    if points.len() < 2 {
+        return vec![];
+    }
+    let cumulative = cumulative_distances(points);
+    let window = stage_length * SNAP_WINDOW;
+    let breaks = stage_breaks(&cumulative, stage_length);
+    let last = breaks.len() - 1;
+
+    let mut stages = vec![];
+    let mut start_index = 0;
+    for (i, index) in breaks.into_iter().enumerate() {
+        let point = &points[index];
+        let city = City::closest(point.lng, point.lat, window, conn).await;
+        let end_index = match &city {
+            // L'arrivée reste l'arrivée demandée
+            Some(city) if i != last => {
+                snap_to_city(points, &cumulative, index, window, city).max(start_index + 1)
+            }
+            _ => index,
+        };
+        stages.push(stage(
+            points,
+            start_index,
+            end_index,
+            city.map(|city| city.name),
+            speed_model,
+        ));
+        start_index = end_index;
+    }
+    stages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::edge::Highway;
+    use std::sync::Arc;
+
+    fn point(lat: f64) -> Point {
+        Point {
+            lng: -73.0,
+            lat,
+            way_id: 0,
+            node_id: 0,
+            length: 0.0,
+            ferry: false,
+            name: None,
+            edge: None,
+        }
+    }
+
+    #[test]
+    fn test_stage_breaks() {
+        // Un point par km sur 250 km
+        let cumulative: Vec<f64> = (0..=250).map(|km| km as f64 * 1000.0).collect();
+        assert_eq!(stage_breaks(&cumulative, 80000.0), vec![80, 160, 250]);
+        // 245 km: la dernière étape de 5 km est fusionnée
+        let cumulative: Vec<f64> = (0..=245).map(|km| km as f64 * 1000.0).collect();
+        assert_eq!(stage_breaks(&cumulative, 80000.0), vec![80, 160, 245]);
+        let cumulative: Vec<f64> = (0..=170).map(|km| km as f64 * 1000.0).collect();
+        assert_eq!(stage_breaks(&cumulative, 80000.0), vec![80, 170]);
+    }
+
+    #[test]
+    fn test_snap_to_city() {
+        let points: Vec<Point> = (0..=100).map(|i| point(45.0 + i as f64 * 0.001)).collect();
+        let cumulative = cumulative_distances(&points);
+        let city = City {
+            name: "Saint-Jean".to_string(),
+            lng: -73.0,
+            lat: 45.055,
+        };
+        // La ville est 5 points plus loin, dans la fenêtre de 1 km
+        assert_eq!(snap_to_city(&points, &cumulative, 50, 1000.0, &city), 55);
+        // Hors de la fenêtre de 300 m, on s'arrête au bord de la fenêtre
+        assert_eq!(snap_to_city(&points, &cumulative, 50, 300.0, &city), 52);
+    }
+
+    #[test]
+    fn test_stage() {
+        let mut points: Vec<Point> = (0..=10).map(|i| point(45.0 + i as f64 * 0.001)).collect();
+        for point in points.iter_mut().skip(6) {
+            point.edge = Some(Arc::new(EdgePoint {
+                highway: Some(Highway::Cycleway),
+                ..EdgePoint::default()
+            }));
+        }
+        let stage = stage(&points, 0, 10, None, &SpeedModel::default());
+        assert!((stage.distance - 1112.0).abs() < 2.0);
+        assert!((stage.dedicated_share - 0.5).abs() < 0.01);
+    }
+}